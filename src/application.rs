@@ -10,10 +10,10 @@ use url::Url;
 
 use crate::options::{VM_REPOSITORY_NAME, VM_REPOSITORY_OWNER};
 use crate::{
-    AppVersion, GToolkit, ImageSeed, ImageVersion, InstallerError, Result, Smalltalk,
-    SmalltalkFlags, DEFAULT_IMAGE_EXTENSION, DEFAULT_IMAGE_NAME, DEFAULT_PHARO_IMAGE, DOCKERFILE,
-    DOCKER_IMAGE_CONTENT_DIRECTORY, GTOOLKIT_REPOSITORY_NAME, GTOOLKIT_REPOSITORY_OWNER,
-    SERIALIZATION_FILE,
+    AppVersion, CustomerLevel, GToolkit, ImageSeed, ImageVersion, InstallerError,
+    LoaderVersionInfo, Result, Smalltalk, SmalltalkFlags, DEFAULT_IMAGE_EXTENSION,
+    DEFAULT_IMAGE_NAME, DEFAULT_PHARO_IMAGE, DOCKERFILE, DOCKER_IMAGE_CONTENT_DIRECTORY,
+    GTOOLKIT_REPOSITORY_NAME, GTOOLKIT_REPOSITORY_OWNER, SERIALIZATION_FILE,
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -26,6 +26,12 @@ pub struct Application {
     image_name: String,
     image_extension: String,
     image_seed: ImageSeed,
+    /// The customer level of the GlamorousToolkit App chosen when building the image
+    #[serde(default)]
+    customer_level: CustomerLevel,
+    /// The versions of gtoolkit and its releaser loaded into the image when building it
+    #[serde(default)]
+    loader_version_info: Option<LoaderVersionInfo>,
 }
 
 impl Application {
@@ -96,9 +102,24 @@ impl Application {
             image_name: DEFAULT_IMAGE_NAME.to_string(),
             image_extension: DEFAULT_IMAGE_EXTENSION.to_string(),
             image_seed,
+            customer_level: CustomerLevel::Auto,
+            loader_version_info: None,
         })
     }
 
+    /// Creates an application for a given workspace without fetching the versions
+    #[cfg(test)]
+    pub fn for_tests(workspace: impl AsRef<Path>) -> Self {
+        let version = Version::parse("v1.0.0").unwrap();
+        Self::new(
+            workspace,
+            version.clone().into(),
+            version.into(),
+            ImageSeed::Url(Url::parse(DEFAULT_PHARO_IMAGE).unwrap()),
+        )
+        .unwrap()
+    }
+
     pub fn is_verbose(&self) -> bool {
         self.verbose
     }
@@ -126,6 +147,26 @@ impl Application {
         self.app_cli_binary.is_some()
     }
 
+    pub fn app_cli_binary(&self) -> Option<&Path> {
+        self.app_cli_binary.as_deref()
+    }
+
+    pub fn customer_level(&self) -> CustomerLevel {
+        self.customer_level
+    }
+
+    pub fn set_customer_level(&mut self, customer_level: CustomerLevel) {
+        self.customer_level = customer_level;
+    }
+
+    pub fn loader_version_info(&self) -> Option<&LoaderVersionInfo> {
+        self.loader_version_info.as_ref()
+    }
+
+    pub fn set_loader_version_info(&mut self, loader_version_info: LoaderVersionInfo) {
+        self.loader_version_info = Some(loader_version_info);
+    }
+
     /// Returns a name of the image (without .image extension)
     pub fn image_name(&self) -> &str {
         self.image_name.as_str()
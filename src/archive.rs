@@ -0,0 +1,639 @@
+use crate::{InstallerError, Result};
+use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc};
+use file_matcher::OneEntry;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::BTreeMap;
+use std::fs::{File, Metadata};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+pub const SOURCE_DATE_EPOCH_ENV: &str = "SOURCE_DATE_EPOCH";
+/// 1980-01-01T00:00:00Z, the earliest modification time that can be stored in a zip archive
+const DEFAULT_SOURCE_DATE_EPOCH: i64 = 315532800;
+/// The compression level of reproducible zip archives
+const REPRODUCIBLE_ZIP_COMPRESSION_LEVEL: i64 = 6;
+
+/// An archive format of the release package, detected from its file name.
+/// Android .apk files are zip archives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar(TarCompression),
+    Deb,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarCompression {
+    Gzip,
+    Zstd,
+}
+
+impl ArchiveFormat {
+    /// Detects the format from the extension: `.tar.gz` (`.tgz`), `.tar.zst` (`.tzst`), `.deb`.
+    /// Anything else is packaged as a `.zip`
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        let file_name = path
+            .as_ref()
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.to_lowercase())
+            .unwrap_or_default();
+
+        if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Self::Tar(TarCompression::Gzip)
+        } else if file_name.ends_with(".tar.zst") || file_name.ends_with(".tzst") {
+            Self::Tar(TarCompression::Zstd)
+        } else if file_name.ends_with(".deb") {
+            Self::Deb
+        } else {
+            Self::Zip
+        }
+    }
+}
+
+/// Settings of a reproducible archive, so that packaging the same files twice produces byte-identical archives:
+/// entries are sorted by name, all of them have the same modification time,
+/// permissions and owners are normalized, and the compression settings are fixed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reproducible {
+    modification_time: DateTime<Utc>,
+}
+
+impl Reproducible {
+    /// Takes the modification time of the entries from the `SOURCE_DATE_EPOCH` environment variable,
+    /// see https://reproducible-builds.org/specs/source-date-epoch/. Defaults to 1980-01-01
+    pub fn from_env() -> Result<Self> {
+        let source_date_epoch = match std::env::var(SOURCE_DATE_EPOCH_ENV) {
+            Ok(value) => value
+                .trim()
+                .parse::<i64>()
+                .map_err(|_| InstallerError::InvalidSourceDateEpoch(value.clone()))?,
+            Err(_) => DEFAULT_SOURCE_DATE_EPOCH,
+        };
+        Self::from_source_date_epoch(source_date_epoch)
+    }
+
+    /// Uses a given number of seconds since the Unix epoch as the modification time of the entries
+    pub fn from_source_date_epoch(source_date_epoch: i64) -> Result<Self> {
+        let modification_time = Utc
+            .timestamp_opt(source_date_epoch, 0)
+            .single()
+            .ok_or_else(|| InstallerError::InvalidSourceDateEpoch(source_date_epoch.to_string()))?;
+
+        Ok(Self { modification_time })
+    }
+
+    pub fn modification_time(&self) -> DateTime<Utc> {
+        self.modification_time
+    }
+
+    /// Zip archives store the time with a precision of two seconds within years 1980 to 2107
+    fn zip_modification_time(&self) -> zip::DateTime {
+        let time = self.modification_time;
+        zip::DateTime::from_date_and_time(
+            time.year().clamp(1980, 2107) as u16,
+            time.month() as u8,
+            time.day() as u8,
+            time.hour() as u8,
+            time.minute() as u8,
+            (time.second() - time.second() % 2) as u8,
+        )
+        .unwrap_or_default()
+    }
+
+    /// Directories and executable files get 0755, other files 0644
+    fn mode(metadata: &Metadata) -> u32 {
+        #[cfg(unix)]
+        let is_executable = {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.permissions().mode() & 0o100 == 0o100
+        };
+        #[cfg(not(unix))]
+        let is_executable = false;
+
+        if metadata.is_dir() || is_executable {
+            0o755
+        } else {
+            0o644
+        }
+    }
+
+    /// Returns a given file or folder followed by everything within it, sorted by name.
+    /// Symlinks are not followed
+    fn sorted_entries(path: &Path) -> Result<Vec<walkdir::DirEntry>> {
+        WalkDir::new(path)
+            .follow_links(false)
+            .sort_by_file_name()
+            .into_iter()
+            .map(|entry| entry.map_err(|error| std::io::Error::from(error).into()))
+            .collect()
+    }
+
+    /// The path of an entry within the archive, placing the root of a walk under a given name
+    fn archive_path(name: &Path, root: &Path, entry: &Path) -> PathBuf {
+        match entry.strip_prefix(root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => name.join(relative),
+            _ => name.to_path_buf(),
+        }
+    }
+
+    /// Appends a file or folder with all its content to a tar archive under a given name
+    pub fn append_to_tar<W: Write>(
+        &self,
+        builder: &mut tar::Builder<W>,
+        name: impl AsRef<Path>,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        for entry in Self::sorted_entries(path)? {
+            let archive_path = Self::archive_path(name.as_ref(), path, entry.path());
+            let metadata = entry.path().symlink_metadata()?;
+
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata_in_mode(&metadata, tar::HeaderMode::Deterministic);
+            header.set_mtime(self.modification_time.timestamp().max(0) as u64);
+            header.set_mode(Self::mode(&metadata));
+
+            if metadata.file_type().is_symlink() {
+                let target = std::fs::read_link(entry.path())?;
+                builder.append_link(&mut header, archive_path, target)?;
+            } else if metadata.is_dir() {
+                builder.append_data(&mut header, archive_path, std::io::empty())?;
+            } else {
+                builder.append_data(&mut header, archive_path, File::open(entry.path())?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends a file or folder with all its content to a zip archive under a given name.
+    /// Files are deflated with a fixed compression level
+    pub fn append_to_zip<W: Write + Seek>(
+        &self,
+        zip: &mut ZipWriter<W>,
+        name: impl AsRef<Path>,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let zip_error = |error| InstallerError::ArchiveWriteError(path.to_path_buf(), error);
+
+        for entry in Self::sorted_entries(path)? {
+            let archive_path = Self::archive_path(name.as_ref(), path, entry.path())
+                .iter()
+                .map(|component| component.to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+                .join("/");
+            let metadata = entry.path().symlink_metadata()?;
+
+            let options = SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .compression_level(Some(REPRODUCIBLE_ZIP_COMPRESSION_LEVEL))
+                .last_modified_time(self.zip_modification_time())
+                .unix_permissions(Self::mode(&metadata))
+                .large_file(metadata.len() > u32::MAX as u64);
+
+            if metadata.file_type().is_symlink() {
+                let target = std::fs::read_link(entry.path())?;
+                zip.add_symlink(
+                    archive_path,
+                    target.to_string_lossy(),
+                    options.unix_permissions(0o777),
+                )
+                .map_err(zip_error)?;
+            } else if metadata.is_dir() {
+                zip.add_directory(archive_path, options)
+                    .map_err(zip_error)?;
+            } else {
+                zip.start_file(archive_path, options).map_err(zip_error)?;
+                std::io::copy(&mut File::open(entry.path())?, zip)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Creates a reproducible zip archive with the given files and folders in its root
+#[derive(Debug, Clone)]
+pub struct ToReproducibleZip {
+    archive: PathBuf,
+    reproducible: Reproducible,
+    entries: Vec<OneEntry>,
+    paths: Vec<PathBuf>,
+}
+
+impl ToReproducibleZip {
+    pub fn new(archive: impl Into<PathBuf>, reproducible: Reproducible) -> Self {
+        Self {
+            archive: archive.into(),
+            reproducible,
+            entries: vec![],
+            paths: vec![],
+        }
+    }
+
+    pub fn one_entries(mut self, entries: Vec<OneEntry>) -> Self {
+        self.entries.extend(entries);
+        self
+    }
+
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    pub fn paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.paths.extend(paths);
+        self
+    }
+
+    pub fn zip(self) -> Result<PathBuf> {
+        let mut paths = self.paths.clone();
+        for entry in &self.entries {
+            paths.push(entry.find()?);
+        }
+        sort_by_file_name(&mut paths);
+
+        if let Some(directory) = self.archive.parent() {
+            if !directory.as_os_str().is_empty() {
+                std::fs::create_dir_all(directory)?;
+            }
+        }
+
+        let mut zip = ZipWriter::new(File::create(&self.archive)?);
+        for path in &paths {
+            let name = path
+                .file_name()
+                .ok_or_else(|| InstallerError::FailedToReadFileName(path.clone()))?;
+            self.reproducible.append_to_zip(&mut zip, name, path)?;
+        }
+        zip.finish()
+            .map_err(|error| InstallerError::ArchiveWriteError(self.archive.clone(), error))?;
+
+        Ok(self.archive)
+    }
+}
+
+/// Sorts the paths by the names they get in the root of an archive
+pub fn sort_by_file_name(paths: &mut [PathBuf]) {
+    paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+}
+
+/// Creates a compressed tar archive with the given files and folders in its root.
+/// Unlike zip, preserves the file modes and does not follow symlinks.
+/// Gzip and zstd are used with their default compression levels
+#[derive(Debug, Clone)]
+pub struct ToTar {
+    archive: PathBuf,
+    compression: TarCompression,
+    reproducible: Option<Reproducible>,
+    entries: Vec<OneEntry>,
+}
+
+impl ToTar {
+    pub fn new(archive: impl Into<PathBuf>, compression: TarCompression) -> Self {
+        Self {
+            archive: archive.into(),
+            compression,
+            reproducible: None,
+            entries: vec![],
+        }
+    }
+
+    pub fn reproducible(mut self, reproducible: Option<Reproducible>) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    pub fn one_entry(mut self, entry: OneEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    pub fn one_entries(mut self, entries: Vec<OneEntry>) -> Self {
+        self.entries.extend(entries);
+        self
+    }
+
+    pub fn tar(self) -> Result<PathBuf> {
+        let mut paths = vec![];
+        for entry in &self.entries {
+            paths.push(entry.find()?);
+        }
+        if self.reproducible.is_some() {
+            sort_by_file_name(&mut paths);
+        }
+
+        if let Some(directory) = self.archive.parent() {
+            if !directory.as_os_str().is_empty() {
+                std::fs::create_dir_all(directory)?;
+            }
+        }
+        let file = File::create(&self.archive)?;
+
+        match self.compression {
+            TarCompression::Gzip => {
+                let encoder =
+                    self.append_entries(GzEncoder::new(file, Compression::default()), &paths)?;
+                encoder.finish()?;
+            }
+            TarCompression::Zstd => {
+                let encoder =
+                    self.append_entries(zstd::stream::write::Encoder::new(file, 0)?, &paths)?;
+                encoder.finish()?;
+            }
+        }
+
+        Ok(self.archive)
+    }
+
+    fn append_entries<W: Write>(&self, writer: W, paths: &[PathBuf]) -> Result<W> {
+        let mut builder = tar::Builder::new(writer);
+        builder.follow_symlinks(false);
+
+        for path in paths {
+            let name = path
+                .file_name()
+                .ok_or_else(|| InstallerError::FailedToReadFileName(path.clone()))?;
+            if let Some(reproducible) = &self.reproducible {
+                reproducible.append_to_tar(&mut builder, name, path)?;
+            } else if path.is_dir() {
+                builder.append_dir_all(name, path)?;
+            } else {
+                builder.append_path_with_name(path, name)?;
+            }
+        }
+
+        Ok(builder.into_inner()?)
+    }
+}
+
+/// Paths of all entries of an existing archive together with the content of selected text files
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveContent {
+    entries: Vec<String>,
+    files: BTreeMap<String, String>,
+}
+
+impl ArchiveContent {
+    /// Reads a .zip (or .apk), .tar.gz, .tar.zst or .deb archive depending on its extension.
+    /// The content of the files with given paths is read as text
+    pub fn read(archive: impl AsRef<Path>, files_to_read: &[&str]) -> Result<Self> {
+        let archive = archive.as_ref();
+        let file = File::open(archive)?;
+
+        let mut content = Self::default();
+        match ArchiveFormat::from_path(archive) {
+            ArchiveFormat::Zip => {
+                let mut zip = zip::ZipArchive::new(file).map_err(|error| {
+                    InstallerError::ArchiveReadError(archive.to_path_buf(), error)
+                })?;
+                for index in 0..zip.len() {
+                    let mut entry = zip.by_index(index).map_err(|error| {
+                        InstallerError::ArchiveReadError(archive.to_path_buf(), error)
+                    })?;
+                    let name = entry.name().to_string();
+                    content.add_entry(name, &mut entry, files_to_read)?;
+                }
+            }
+            ArchiveFormat::Tar(TarCompression::Gzip) => {
+                content.read_tar(GzDecoder::new(file), files_to_read)?;
+            }
+            ArchiveFormat::Tar(TarCompression::Zstd) => {
+                content.read_tar(zstd::stream::read::Decoder::new(file)?, files_to_read)?;
+            }
+            ArchiveFormat::Deb => {
+                // only the installed files are of interest, they are in the data archive
+                let mut deb = ar::Archive::new(file);
+                while let Some(entry) = deb.next_entry() {
+                    let entry = entry?;
+                    if entry.header().identifier() == b"data.tar.gz" {
+                        content.read_tar(GzDecoder::new(entry), files_to_read)?;
+                    }
+                }
+            }
+        }
+        Ok(content)
+    }
+
+    fn read_tar(&mut self, reader: impl Read, files_to_read: &[&str]) -> Result<()> {
+        let mut tar = tar::Archive::new(reader);
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().to_string();
+            self.add_entry(name, &mut entry, files_to_read)?;
+        }
+        Ok(())
+    }
+
+    fn add_entry(
+        &mut self,
+        name: String,
+        reader: &mut impl Read,
+        files_to_read: &[&str],
+    ) -> Result<()> {
+        let name = name
+            .trim_start_matches("./")
+            .trim_end_matches('/')
+            .to_string();
+        if name.is_empty() {
+            return Ok(());
+        }
+
+        if files_to_read.contains(&name.as_str()) {
+            let mut file_content = String::new();
+            reader.read_to_string(&mut file_content)?;
+            self.files.insert(name.clone(), file_content);
+        }
+        self.entries.push(name);
+        Ok(())
+    }
+
+    /// Returns the paths of all entries within the archive, using `/` as a separator
+    pub fn entries(&self) -> &[String] {
+        self.entries.as_slice()
+    }
+
+    pub fn file_content(&self, name: &str) -> Option<&str> {
+        self.files.get(name).map(|content| content.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use file_matcher::{FileNamed, FolderNamed};
+
+    /// 2023-11-14T22:13:20Z
+    const SOURCE_DATE_EPOCH: i64 = 1700000000;
+
+    /// Creates the same tree of files in a given order, with different modification times
+    /// and permissions that normalize to the same modes
+    fn create_tree(root: &Path, reversed: bool, modes: [u32; 2]) {
+        let mut files = vec![
+            ("app/bin/launcher", "#!/bin/sh"),
+            ("app/lib/library.so", "library"),
+            ("app/README.md", "readme"),
+            ("GlamorousToolkit.image", "image"),
+        ];
+        if reversed {
+            files.reverse();
+        }
+
+        for (index, (file, content)) in files.into_iter().enumerate() {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, content).unwrap();
+            filetime::set_file_mtime(
+                &path,
+                filetime::FileTime::from_unix_time(1600000000 + index as i64 * 1000, 0),
+            )
+            .unwrap();
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let [executable, regular] = modes;
+            std::fs::set_permissions(
+                root.join("app/bin/launcher"),
+                std::fs::Permissions::from_mode(executable),
+            )
+            .unwrap();
+            std::fs::set_permissions(
+                root.join("GlamorousToolkit.image"),
+                std::fs::Permissions::from_mode(regular),
+            )
+            .unwrap();
+        }
+        #[cfg(not(unix))]
+        let _ = modes;
+    }
+
+    fn reproducible() -> Reproducible {
+        Reproducible::from_source_date_epoch(SOURCE_DATE_EPOCH).unwrap()
+    }
+
+    fn zip_tree(root: &Path, archive: &Path) -> Vec<u8> {
+        ToReproducibleZip::new(archive, reproducible())
+            .one_entries(vec![
+                FolderNamed::exact("app").within(root),
+                FileNamed::exact("GlamorousToolkit.image")
+                    .within(root)
+                    .into(),
+            ])
+            .zip()
+            .unwrap();
+        std::fs::read(archive).unwrap()
+    }
+
+    fn tar_tree(root: &Path, archive: &Path, compression: TarCompression) -> Vec<u8> {
+        ToTar::new(archive, compression)
+            .reproducible(Some(reproducible()))
+            .one_entries(vec![
+                FileNamed::exact("GlamorousToolkit.image")
+                    .within(root)
+                    .into(),
+                FolderNamed::exact("app").within(root),
+            ])
+            .tar()
+            .unwrap();
+        std::fs::read(archive).unwrap()
+    }
+
+    #[test]
+    fn same_trees_produce_identical_archives() {
+        let directory = tempfile::tempdir().unwrap();
+        let first = directory.path().join("first");
+        let second = directory.path().join("second");
+        create_tree(&first, false, [0o755, 0o644]);
+        create_tree(&second, true, [0o700, 0o600]);
+
+        let output = directory.path().join("output");
+        assert_eq!(
+            zip_tree(&first, &output.join("first.zip")),
+            zip_tree(&second, &output.join("second.zip"))
+        );
+        assert_eq!(
+            tar_tree(&first, &output.join("first.tar.gz"), TarCompression::Gzip),
+            tar_tree(&second, &output.join("second.tar.gz"), TarCompression::Gzip)
+        );
+        assert_eq!(
+            tar_tree(&first, &output.join("first.tar.zst"), TarCompression::Zstd),
+            tar_tree(
+                &second,
+                &output.join("second.tar.zst"),
+                TarCompression::Zstd
+            )
+        );
+    }
+
+    #[test]
+    fn archives_entries_sorted_with_normalized_metadata() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path().join("tree");
+        create_tree(&root, true, [0o700, 0o600]);
+
+        let archive = directory.path().join("tree.tar.gz");
+        tar_tree(&root, &archive, TarCompression::Gzip);
+
+        let mut tar = tar::Archive::new(GzDecoder::new(File::open(&archive).unwrap()));
+        let entries = tar
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let header = entry.header();
+                assert_eq!(header.mtime().unwrap(), SOURCE_DATE_EPOCH as u64);
+                assert_eq!(header.uid().unwrap(), 0);
+                assert_eq!(header.gid().unwrap(), 0);
+                (
+                    entry.path().unwrap().to_string_lossy().to_string(),
+                    header.mode().unwrap(),
+                )
+            })
+            .collect::<Vec<(String, u32)>>();
+
+        let expected_modes = if cfg!(unix) {
+            [0o644, 0o755]
+        } else {
+            [0o644, 0o644]
+        };
+        assert_eq!(
+            entries,
+            vec![
+                ("GlamorousToolkit.image".to_string(), expected_modes[0]),
+                ("app".to_string(), 0o755),
+                ("app/README.md".to_string(), 0o644),
+                ("app/bin".to_string(), 0o755),
+                ("app/bin/launcher".to_string(), expected_modes[1]),
+                ("app/lib".to_string(), 0o755),
+                ("app/lib/library.so".to_string(), 0o644),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_source_date_epoch_changes_the_archive() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path().join("tree");
+        create_tree(&root, false, [0o755, 0o644]);
+
+        let first = zip_tree(&root, &directory.path().join("first.zip"));
+        let other = directory.path().join("other.zip");
+        ToReproducibleZip::new(
+            &other,
+            Reproducible::from_source_date_epoch(SOURCE_DATE_EPOCH + 86400).unwrap(),
+        )
+        .path(root.join("app"))
+        .path(root.join("GlamorousToolkit.image"))
+        .zip()
+        .unwrap();
+
+        assert_ne!(first, std::fs::read(other).unwrap());
+    }
+}
@@ -0,0 +1,427 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use walkdir::WalkDir;
+
+use crate::{sort_by_file_name, InstallerError, PlatformOS, Reproducible, Result};
+
+/// The name of the Debian package, its launcher and desktop entry
+const DEBIAN_PACKAGE_NAME: &str = "glamoroustoolkit";
+/// The installation directory of the toolkit within the Debian package
+pub const DEBIAN_INSTALLATION_DIRECTORY: &str = "opt/glamoroustoolkit";
+const MAINTAINER: &str = "feenk gmbh <contact@feenk.com>";
+const HOMEPAGE: &str = "https://gtoolkit.com";
+/// A themed icon of the desktop entry, the GlamorousToolkit App for Linux does not ship its own
+const DESKTOP_ICON: &str = "applications-development";
+
+/// Builds a Debian binary package (.deb) that installs the given files and folders
+/// into `/opt/glamoroustoolkit`, together with a launcher in `/usr/bin` and a desktop entry.
+/// The package is assembled in Rust and does not require `dpkg-deb`
+#[derive(Debug, Clone)]
+pub struct ToDebian {
+    package: PathBuf,
+    target: PlatformOS,
+    version: String,
+    app_version: String,
+    reproducible: Option<Reproducible>,
+    entries: Vec<PathBuf>,
+}
+
+impl ToDebian {
+    pub fn new(
+        package: impl Into<PathBuf>,
+        target: PlatformOS,
+        version: impl Into<String>,
+        app_version: impl Into<String>,
+    ) -> Self {
+        Self {
+            package: package.into(),
+            target,
+            version: version.into(),
+            app_version: app_version.into(),
+            reproducible: None,
+            entries: vec![],
+        }
+    }
+
+    pub fn reproducible(mut self, reproducible: Option<Reproducible>) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    pub fn entries(mut self, entries: Vec<PathBuf>) -> Self {
+        self.entries.extend(entries);
+        self
+    }
+
+    pub fn deb(self) -> Result<PathBuf> {
+        let architecture = match self.target {
+            PlatformOS::LinuxX8664 => "amd64",
+            PlatformOS::LinuxAarch64 => "arm64",
+            _ => return InstallerError::UnsupportedDebianTarget(self.target).into(),
+        };
+
+        let directory = self
+            .package
+            .parent()
+            .filter(|directory| !directory.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        std::fs::create_dir_all(directory)?;
+
+        // the data archive contains the whole image, so it is written to a temporary file
+        // next to the package instead of memory. The file is removed even if packaging fails
+        let data_archive = tempfile::NamedTempFile::new_in(directory)?;
+        self.write_data_archive(data_archive.path())?;
+        let control_archive = self.control_archive(architecture)?;
+
+        let mut deb = ar::Builder::new(File::create(&self.package)?);
+        Self::append_member(&mut deb, "debian-binary", 4, b"2.0\n".as_slice())?;
+        Self::append_member(
+            &mut deb,
+            "control.tar.gz",
+            control_archive.len() as u64,
+            control_archive.as_slice(),
+        )?;
+        Self::append_member(
+            &mut deb,
+            "data.tar.gz",
+            std::fs::metadata(data_archive.path())?.len(),
+            File::open(data_archive.path())?,
+        )?;
+        data_archive.close()?;
+
+        Ok(self.package)
+    }
+
+    fn control_archive(&self, architecture: &str) -> Result<Vec<u8>> {
+        let control = format!(
+            "Package: {package}\n\
+             Version: {version}\n\
+             Architecture: {architecture}\n\
+             Maintainer: {maintainer}\n\
+             Installed-Size: {installed_size}\n\
+             Section: devel\n\
+             Priority: optional\n\
+             Homepage: {homepage}\n\
+             Description: Glamorous Toolkit, the moldable development environment\n \
+             Glamorous Toolkit v{version} with the GlamorousToolkit App v{app_version}.\n",
+            package = DEBIAN_PACKAGE_NAME,
+            version = &self.version,
+            architecture = architecture,
+            maintainer = MAINTAINER,
+            installed_size = self.installed_size()?,
+            homepage = HOMEPAGE,
+            app_version = &self.app_version,
+        );
+
+        let mut tar = Self::tar_builder(vec![]);
+        Self::append_directory(&mut tar, "./")?;
+        Self::append_file(&mut tar, "./control", control.as_bytes(), 0o644)?;
+        Ok(tar.into_inner()?.finish()?)
+    }
+
+    fn write_data_archive(&self, data_archive: &Path) -> Result<()> {
+        let mut tar = Self::tar_builder(File::create(data_archive)?);
+
+        for directory in [
+            "./",
+            "./opt/",
+            "./usr/",
+            "./usr/bin/",
+            "./usr/share/",
+            "./usr/share/applications/",
+        ] {
+            Self::append_directory(&mut tar, directory)?;
+        }
+        Self::append_directory(&mut tar, &format!("./{}/", DEBIAN_INSTALLATION_DIRECTORY))?;
+
+        let mut entries = self.entries.clone();
+        if self.reproducible.is_some() {
+            sort_by_file_name(&mut entries);
+        }
+
+        for entry in &entries {
+            let name = entry
+                .file_name()
+                .ok_or_else(|| InstallerError::FailedToReadFileName(entry.clone()))?;
+            let archive_path = Path::new(".")
+                .join(DEBIAN_INSTALLATION_DIRECTORY)
+                .join(name);
+            if let Some(reproducible) = &self.reproducible {
+                reproducible.append_to_tar(&mut tar, archive_path, entry)?;
+            } else if entry.is_dir() {
+                tar.append_dir_all(archive_path, entry)?;
+            } else {
+                tar.append_path_with_name(entry, archive_path)?;
+            }
+        }
+
+        Self::append_file(
+            &mut tar,
+            &format!("./usr/bin/{}", DEBIAN_PACKAGE_NAME),
+            self.launcher().as_bytes(),
+            0o755,
+        )?;
+        Self::append_file(
+            &mut tar,
+            &format!("./usr/share/applications/{}.desktop", DEBIAN_PACKAGE_NAME),
+            Self::desktop_entry().as_bytes(),
+            0o644,
+        )?;
+
+        tar.into_inner()?.finish()?;
+        Ok(())
+    }
+
+    /// The installation in /opt is read-only, so the launcher gives every user
+    /// a writable copy of the image and starts the App from there.
+    /// When the package is upgraded, the copy of the previous version is moved aside and replaced
+    fn launcher(&self) -> String {
+        format!(
+            "#!/bin/sh\n\
+             set -e\n\
+             GT_INSTALLATION=\"/{installation}\"\n\
+             GT_HOME=\"${{XDG_DATA_HOME:-$HOME/.local/share}}/{package}\"\n\
+             GT_VERSION=\"{version}\"\n\
+             if [ \"$(cat \"$GT_HOME/.version\" 2>/dev/null || true)\" != \"$GT_VERSION\" ]; then\n\
+             \x20 if [ -d \"$GT_HOME\" ]; then\n\
+             \x20   rm -rf \"$GT_HOME.previous\"\n\
+             \x20   mv \"$GT_HOME\" \"$GT_HOME.previous\"\n\
+             \x20   echo \"Glamorous Toolkit was updated to v$GT_VERSION, the previous image was moved to $GT_HOME.previous\" >&2\n\
+             \x20 fi\n\
+             \x20 mkdir -p \"$GT_HOME\"\n\
+             \x20 cp -R \"$GT_INSTALLATION\"/*.image \"$GT_INSTALLATION\"/*.changes \"$GT_INSTALLATION\"/*.sources \"$GT_HOME\"/\n\
+             \x20 if [ -d \"$GT_INSTALLATION/gt-extra\" ]; then cp -R \"$GT_INSTALLATION/gt-extra\" \"$GT_HOME\"/; fi\n\
+             \x20 echo \"$GT_VERSION\" > \"$GT_HOME/.version\"\n\
+             fi\n\
+             cd \"$GT_HOME\"\n\
+             exec \"$GT_INSTALLATION/bin/GlamorousToolkit\" \"$@\"\n",
+            installation = DEBIAN_INSTALLATION_DIRECTORY,
+            package = DEBIAN_PACKAGE_NAME,
+            version = &self.version,
+        )
+    }
+
+    fn desktop_entry() -> String {
+        format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=Glamorous Toolkit\n\
+             Comment=The moldable development environment\n\
+             Exec={package}\n\
+             Icon={icon}\n\
+             Terminal=false\n\
+             Categories=Development;IDE;\n",
+            package = DEBIAN_PACKAGE_NAME,
+            icon = DESKTOP_ICON,
+        )
+    }
+
+    /// Returns the size of the installed files in KiB, as expected by the control file
+    fn installed_size(&self) -> Result<u64> {
+        let mut size = 0;
+        for entry in &self.entries {
+            for each in WalkDir::new(entry).follow_links(false) {
+                let each = each.map_err(std::io::Error::from)?;
+                if each.file_type().is_file() {
+                    size += each.metadata().map_err(std::io::Error::from)?.len();
+                }
+            }
+        }
+        Ok(size.div_ceil(1024))
+    }
+
+    fn tar_builder<W: Write>(writer: W) -> tar::Builder<GzEncoder<W>> {
+        let mut tar = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+        tar.follow_symlinks(false);
+        // installed files must be owned by root, not by the user that built the package
+        tar.mode(tar::HeaderMode::Deterministic);
+        tar
+    }
+
+    fn append_member(
+        deb: &mut ar::Builder<File>,
+        name: &str,
+        size: u64,
+        content: impl Read,
+    ) -> Result<()> {
+        let mut header = ar::Header::new(name.as_bytes().to_vec(), size);
+        header.set_mode(0o100644);
+        deb.append(&header, content)?;
+        Ok(())
+    }
+
+    fn append_directory(tar: &mut tar::Builder<impl Write>, path: &str) -> Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        header.set_mtime(0);
+        tar.append_data(&mut header, path, std::io::empty())?;
+        Ok(())
+    }
+
+    fn append_file(
+        tar: &mut tar::Builder<impl Write>,
+        path: &str,
+        content: &[u8],
+        mode: u32,
+    ) -> Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_mode(mode);
+        header.set_size(content.len() as u64);
+        header.set_mtime(0);
+        tar.append_data(&mut header, path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+
+    /// Returns the names and the content of the members of an ar archive
+    fn read_members(deb: &Path) -> Vec<(String, Vec<u8>)> {
+        let mut archive = ar::Archive::new(File::open(deb).unwrap());
+        let mut members = vec![];
+        while let Some(member) = archive.next_entry() {
+            let mut member = member.unwrap();
+            let name = String::from_utf8(member.header().identifier().to_vec()).unwrap();
+            let mut content = vec![];
+            member.read_to_end(&mut content).unwrap();
+            members.push((name, content));
+        }
+        members
+    }
+
+    /// Returns the paths and modes of the entries of a gzipped tar archive
+    fn read_tar_entries(content: &[u8]) -> Vec<(String, u32)> {
+        let mut tar = tar::Archive::new(GzDecoder::new(content));
+        tar.entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (
+                    String::from_utf8_lossy(&entry.path_bytes()).to_string(),
+                    entry.header().mode().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn read_tar_file(content: &[u8], path: &str) -> String {
+        let mut tar = tar::Archive::new(GzDecoder::new(content));
+        let mut entry = tar
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .find(|entry| entry.path_bytes().as_ref() == path.as_bytes())
+            .unwrap();
+        let mut file = String::new();
+        entry.read_to_string(&mut file).unwrap();
+        file
+    }
+
+    #[test]
+    fn packages_the_entries_into_a_debian_package() {
+        let directory = tempfile::tempdir().unwrap();
+        let image = directory.path().join("GlamorousToolkit.image");
+        std::fs::write(&image, vec![0u8; 2048]).unwrap();
+
+        let output = directory.path().join("output");
+        let deb = ToDebian::new(
+            output.join("glamoroustoolkit.deb"),
+            PlatformOS::LinuxX8664,
+            "1.2.3",
+            "1.0.0",
+        )
+        .entries(vec![image])
+        .deb()
+        .unwrap();
+
+        let members = read_members(&deb);
+        assert_eq!(
+            members
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["debian-binary", "control.tar.gz", "data.tar.gz"]
+        );
+        assert_eq!(members[0].1, b"2.0\n");
+
+        let control = read_tar_file(&members[1].1, "control");
+        for field in [
+            "Package: glamoroustoolkit\n",
+            "Version: 1.2.3\n",
+            "Architecture: amd64\n",
+            "Maintainer: feenk gmbh <contact@feenk.com>\n",
+            "Installed-Size: 2\n",
+            "Homepage: https://gtoolkit.com\n",
+        ] {
+            assert!(
+                control.contains(field),
+                "{} is missing in\n{}",
+                field,
+                control
+            );
+        }
+
+        let data_entries = read_tar_entries(&members[2].1);
+        assert!(data_entries.contains(&(
+            "opt/glamoroustoolkit/GlamorousToolkit.image".to_string(),
+            0o644
+        )));
+        assert!(data_entries.contains(&("usr/bin/glamoroustoolkit".to_string(), 0o755)));
+        assert!(data_entries.contains(&(
+            "usr/share/applications/glamoroustoolkit.desktop".to_string(),
+            0o644
+        )));
+
+        // the temporary data archive is removed
+        assert_eq!(std::fs::read_dir(&output).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn removes_the_data_archive_when_packaging_fails() {
+        let directory = tempfile::tempdir().unwrap();
+        let image = directory.path().join("GlamorousToolkit.image");
+        std::fs::write(&image, b"image").unwrap();
+
+        // the package can not be created where a folder already exists
+        let output = directory.path().join("output");
+        std::fs::create_dir_all(output.join("glamoroustoolkit.deb")).unwrap();
+
+        assert!(ToDebian::new(
+            output.join("glamoroustoolkit.deb"),
+            PlatformOS::LinuxAarch64,
+            "1.2.3",
+            "1.0.0",
+        )
+        .entries(vec![image])
+        .deb()
+        .is_err());
+        assert_eq!(std::fs::read_dir(&output).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn debian_packages_are_only_for_linux() {
+        let directory = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            ToDebian::new(
+                directory.path().join("glamoroustoolkit.deb"),
+                PlatformOS::MacOSAarch64,
+                "1.2.3",
+                "1.0.0",
+            )
+            .deb(),
+            Err(InstallerError::UnsupportedDebianTarget(
+                PlatformOS::MacOSAarch64
+            ))
+        ));
+    }
+}
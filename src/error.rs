@@ -69,6 +69,98 @@ pub enum InstallerError {
     FailedToReadFileName(PathBuf),
     #[error("Failed to read the file extension of {0}")]
     FailedToReadFileExtension(PathBuf),
+    #[error("Package pattern {0:?} does not match any package with examples or tests. See `list-packages` for available packages")]
+    PackagePatternDidNotMatch(String),
+    #[error("Failed to parse JUnit XML file {0}")]
+    JUnitParseError(PathBuf, #[source] serde_xml_rs::Error),
+    #[error("Failed to read the time budget file {0}")]
+    TimeBudgetReadError(PathBuf, #[source] std::io::Error),
+    #[error("Failed to parse the time budget {1:?} of {0}")]
+    TimeBudgetParseError(String, String, #[source] parse_duration::parse::Error),
+    #[error("Testing took longer than the time budget:\n{0}")]
+    TimeBudgetExceeded(String),
+    #[error("Failed to read or write the test history {0}")]
+    TestHistoryError(PathBuf, #[source] serde_json::Error),
+    #[error("Example or test {0} is not found in the test history")]
+    TestHistoryExampleNotFound(String),
+    #[error("Android NDK or SDK error. Make sure ANDROID_HOME and ANDROID_NDK_ROOT are set")]
+    NdkError(#[from] ndk_build::error::NdkError),
+    #[error("{0:?} is not an Android target")]
+    UnsupportedAndroidTarget(crate::PlatformOS),
+    #[error("AndroidManifest.xml does not exist: {0}")]
+    AndroidManifestNotFound(PathBuf),
+    #[error("Failed to parse AndroidManifest.xml {0}")]
+    AndroidManifestParseError(PathBuf, #[source] serde_xml_rs::Error),
+    #[error("The GlamorousToolkit App for Android does not contain any libraries in {0}")]
+    AndroidLibrariesNotFound(PathBuf),
+    #[error("Android packages must have the .apk extension: {0}")]
+    AndroidPackageExtension(PathBuf),
+    #[error("The Android package was not created: {0}")]
+    AndroidPackageNotCreated(PathBuf),
+    #[error("Failed to write the release manifest {0}")]
+    ManifestSerializationError(PathBuf, #[source] serde_json::Error),
+    #[error("Failed to read the release manifest {0}")]
+    ManifestDeserializationError(PathBuf, #[source] serde_json::Error),
+    #[error("Both {1:?} and {2:?} would be packaged into {0}. Use {{{{os}}}} and {{{{arch}}}} in the release path")]
+    ReleasePackagePathConflict(PathBuf, crate::PlatformOS, crate::PlatformOS),
+    #[error("Invalid package path template {0}")]
+    InvalidPathTemplate(PathBuf, #[source] mustache::Error),
+    #[error("Failed to read or write the release notes {0}")]
+    ReleaseNotesError(PathBuf, #[source] serde_json::Error),
+    #[error("Failed to read the archive {0}")]
+    ArchiveReadError(PathBuf, #[source] zip::result::ZipError),
+    #[error("Package {0} is not valid, found {1} problem(s)")]
+    PackageVerificationFailed(PathBuf, usize),
+    #[error("Tentative package {0} does not contain a manifest. Package it again with a newer gt-installer")]
+    TentativeManifestNotFound(PathBuf),
+    #[error("Failed to read the manifest of the tentative package {0}")]
+    TentativeManifestError(PathBuf, #[source] serde_json::Error),
+    #[error("Tentative package {0} is incomplete or corrupted:\n{1}")]
+    TentativePackageCorrupted(PathBuf, String),
+    #[error("Debian packages can only be created for Linux, not for {0:?}")]
+    UnsupportedDebianTarget(crate::PlatformOS),
+    #[error("Failed to serialize the software bill of materials {0}")]
+    SbomSerializationError(PathBuf, #[source] serde_json::Error),
+    #[error("Package {0} does not exist")]
+    PackageNotFound(PathBuf),
+    #[error("The {0} environment variable must be set to publish")]
+    PublishCredentialsNotFound(String),
+    #[error("Request to {0} failed with status code {1}\n{2}")]
+    PublishRequestFailed(Url, StatusCode, String),
+    #[error("Invalid request header: {0}")]
+    InvalidRequestHeader(String),
+    #[error("SOURCE_DATE_EPOCH must be a number of seconds since 1970-01-01, not {0}")]
+    InvalidSourceDateEpoch(String),
+    #[error("Failed to write archive entry {0}")]
+    ArchiveWriteError(PathBuf, #[source] zip::result::ZipError),
+    #[error("Failed to read the secret key to sign packages")]
+    SigningKeyError(#[source] minisign::PError),
+    #[error("Failed to read the public key to verify signatures")]
+    PublicKeyError(#[source] minisign::PError),
+    #[error("A public key is required to verify signatures, pass it with --public-key or in the FEENK_PUBLIC_KEY environment variable")]
+    PublicKeyNotConfigured,
+    #[error("Failed to sign {0}")]
+    SigningError(PathBuf, #[source] minisign::PError),
+    #[error("Signature {0} does not exist")]
+    SignatureNotFound(PathBuf),
+    #[error("Signature of {0} is not valid")]
+    SignatureVerificationFailed(PathBuf, #[source] minisign::PError),
+    #[error("Snapshot {0} does not exist")]
+    SnapshotNotFound(String),
+    #[error("Snapshot {0} already exists")]
+    SnapshotAlreadyExists(String),
+    #[error("{0} is not a valid snapshot name, it must not be empty, start with a dot or contain path separators")]
+    InvalidSnapshotName(String),
+    #[error("Failed to read or write the snapshot description {0}")]
+    SnapshotSerializationError(PathBuf, #[source] serde_json::Error),
+    #[error("Can not rename the image, {0} already exists")]
+    ImageAlreadyExists(PathBuf),
+    #[error("Image {0} does not exist")]
+    ImageNotFound(PathBuf),
+    #[error("GitHub request failed")]
+    GitHubError(#[from] octocrab::Error),
+    #[error("{0} is not a valid image name, it must not be empty, start with a dot or contain path separators")]
+    InvalidImageName(String),
 }
 
 impl<T> From<InstallerError> for std::result::Result<T, InstallerError> {
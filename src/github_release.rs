@@ -0,0 +1,345 @@
+use std::path::Path;
+
+use octocrab::{Octocrab, OctocrabBuilder};
+use reqwest::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::{Method, Response};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::{InstallerError, Result};
+
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+pub const GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubReleaseInfo {
+    pub id: u64,
+    pub upload_url: String,
+    #[serde(default)]
+    pub assets: Vec<GitHubAsset>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubAsset {
+    pub id: u64,
+    pub name: String,
+    pub size: u64,
+    /// The API url of the asset, used to download or delete it
+    pub url: String,
+    /// The checksum of the asset in a form of `sha256:<hex>`, reported by newer GitHub versions
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+/// A release of a GitHub repository identified by its tag.
+/// The API base url is configurable to support GitHub Enterprise or a local stand-in
+#[derive(Debug, Clone)]
+pub struct GitHubRelease {
+    octocrab: Octocrab,
+    owner: String,
+    repository: String,
+    tag: String,
+}
+
+impl GitHubRelease {
+    /// Creates a release with the token from the `GITHUB_TOKEN` environment variable
+    pub fn from_env(
+        api_url: Url,
+        owner: impl Into<String>,
+        repository: impl Into<String>,
+        tag: impl Into<String>,
+    ) -> Result<Self> {
+        let token = std::env::var(GITHUB_TOKEN_ENV).map_err(|_| {
+            InstallerError::PublishCredentialsNotFound(GITHUB_TOKEN_ENV.to_string())
+        })?;
+
+        Self::new(api_url, token, owner, repository, tag)
+    }
+
+    pub fn new(
+        mut api_url: Url,
+        token: impl Into<String>,
+        owner: impl Into<String>,
+        repository: impl Into<String>,
+        tag: impl Into<String>,
+    ) -> Result<Self> {
+        // routes are joined to the base url, so a path such as `/api/v3` of GitHub Enterprise must end with a slash
+        if !api_url.path().ends_with('/') {
+            api_url.set_path(&format!("{}/", api_url.path()));
+        }
+
+        let octocrab = OctocrabBuilder::new()
+            .personal_token(token.into())
+            .base_url(api_url)?
+            .build()?;
+
+        Ok(Self {
+            octocrab,
+            owner: owner.into(),
+            repository: repository.into(),
+            tag: tag.into(),
+        })
+    }
+
+    /// Returns the release with the tag, creating it if it does not exist yet
+    pub async fn find_or_create(&self) -> Result<GitHubReleaseInfo> {
+        let route = format!("{}/releases/tags/{}", self.repository_route(), &self.tag);
+        match self.octocrab.get(route, None::<&()>).await {
+            Ok(release) => return Ok(release),
+            Err(octocrab::Error::GitHub { source, .. }) if source.message == "Not Found" => {}
+            Err(error) => return Err(error.into()),
+        }
+
+        let route = format!("{}/releases", self.repository_route());
+        let release = self
+            .octocrab
+            .post(
+                route,
+                Some(&serde_json::json!({
+                    "tag_name": &self.tag,
+                    "name": &self.tag,
+                })),
+            )
+            .await?;
+        Ok(release)
+    }
+
+    /// Computes the SHA-256 checksum of an uploaded asset.
+    /// Uses the digest reported by GitHub when available, otherwise downloads the asset
+    pub async fn asset_sha256(&self, asset: &GitHubAsset) -> Result<String> {
+        if let Some(sha256) = asset
+            .digest
+            .as_ref()
+            .and_then(|digest| digest.strip_prefix("sha256:"))
+        {
+            return Ok(sha256.to_string());
+        }
+
+        let url = Url::parse(&asset.url)?;
+        let mut response = self
+            .octocrab
+            .execute(
+                self.octocrab
+                    .request_builder(url.clone(), Method::GET)
+                    .header(ACCEPT, "application/octet-stream"),
+            )
+            .await?;
+        if !response.status().is_success() {
+            return Self::check(url, response).await;
+        }
+
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    pub async fn delete_asset(&self, asset: &GitHubAsset) -> Result<()> {
+        let url = Url::parse(&asset.url)?;
+        let response = self
+            .octocrab
+            .execute(self.octocrab.request_builder(url.clone(), Method::DELETE))
+            .await?;
+        Self::check(url, response).await
+    }
+
+    /// Uploads a file as an asset of the release under a given name
+    pub async fn upload_asset(
+        &self,
+        release: &GitHubReleaseInfo,
+        name: &str,
+        file: impl AsRef<Path>,
+    ) -> Result<()> {
+        let file = file.as_ref();
+        // the upload url is a hypermedia template: https://uploads.github.com/repos/o/r/releases/1/assets{?name,label}
+        let upload_url = release.upload_url.split('{').next().unwrap_or_default();
+        let mut url = Url::parse(upload_url)?;
+        url.query_pairs_mut().append_pair("name", name);
+
+        let response = self
+            .octocrab
+            .execute(
+                self.octocrab
+                    .request_builder(url.clone(), Method::POST)
+                    .header(CONTENT_TYPE, "application/octet-stream")
+                    .header(CONTENT_LENGTH, std::fs::metadata(file)?.len())
+                    .body(tokio::fs::File::open(file).await?),
+            )
+            .await?;
+        Self::check(url, response).await
+    }
+
+    fn repository_route(&self) -> String {
+        format!("repos/{}/{}", &self.owner, &self.repository)
+    }
+
+    /// Fails with the body of the response if the request was not successful
+    async fn check<T: Default>(url: Url, response: Response) -> Result<T> {
+        let status = response.status();
+        if !status.is_success() {
+            return InstallerError::PublishRequestFailed(url, status, response.text().await?)
+                .into();
+        }
+        Ok(T::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_stub::{HttpStub, StubRequest, StubResponse};
+
+    fn release_json(stub_url: &Url, assets: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "id": 1,
+            "upload_url": format!("{}repos/feenkcom/gtoolkit/releases/1/assets{{?name,label}}", stub_url),
+            "assets": assets,
+        })
+    }
+
+    fn release(stub: &HttpStub) -> GitHubRelease {
+        GitHubRelease::new(stub.url(), "token", "feenkcom", "gtoolkit", "v1.0.0").unwrap()
+    }
+
+    #[tokio::test]
+    async fn creates_a_missing_release() {
+        let stub = HttpStub::start(|request: &StubRequest| match request.method.as_str() {
+            "GET" => StubResponse::new(404).json(serde_json::json!({
+                "message": "Not Found",
+                "documentation_url": "https://docs.github.com/rest",
+            })),
+            _ => StubResponse::new(201).json(serde_json::json!({
+                "id": 2,
+                "upload_url": "http://localhost/assets{?name,label}",
+            })),
+        })
+        .await;
+
+        let created = release(&stub).find_or_create().await.unwrap();
+        assert_eq!(created.id, 2);
+        assert!(created.assets.is_empty());
+
+        let requests = stub.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].path,
+            "/repos/feenkcom/gtoolkit/releases/tags/v1.0.0"
+        );
+        assert_eq!(requests[1].method, "POST");
+        assert_eq!(requests[1].path, "/repos/feenkcom/gtoolkit/releases");
+        let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(body["tag_name"], "v1.0.0");
+        assert_eq!(requests[1].headers["authorization"], "Bearer token");
+    }
+
+    #[tokio::test]
+    async fn finds_an_existing_release() {
+        let stub = HttpStub::start(|_| {
+            StubResponse::new(200).json(serde_json::json!({
+                "id": 1,
+                "upload_url": "http://localhost/assets{?name,label}",
+                "assets": [{
+                    "id": 3,
+                    "name": "GlamorousToolkit.zip",
+                    "size": 7,
+                    "url": "http://localhost/assets/3",
+                    "digest": "sha256:abc",
+                }],
+            }))
+        })
+        .await;
+
+        let github = release(&stub);
+        let found = github.find_or_create().await.unwrap();
+        assert_eq!(found.id, 1);
+        assert_eq!(github.asset_sha256(&found.assets[0]).await.unwrap(), "abc");
+        assert_eq!(stub.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn downloads_an_asset_without_a_digest() {
+        let stub = HttpStub::start(|request| match request.method.as_str() {
+            "GET" => {
+                let mut response = StubResponse::new(200);
+                response.body = b"package".to_vec();
+                response
+            }
+            _ => StubResponse::new(204),
+        })
+        .await;
+
+        let github = release(&stub);
+        let asset = GitHubAsset {
+            id: 3,
+            name: "GlamorousToolkit.zip".to_string(),
+            size: 7,
+            url: format!("{}repos/feenkcom/gtoolkit/releases/assets/3", stub.url()),
+            digest: None,
+        };
+
+        assert_eq!(
+            github.asset_sha256(&asset).await.unwrap(),
+            format!("{:x}", Sha256::digest(b"package"))
+        );
+        github.delete_asset(&asset).await.unwrap();
+
+        let requests = stub.requests();
+        assert_eq!(requests[0].headers["accept"], "application/octet-stream");
+        assert_eq!(requests[1].method, "DELETE");
+        assert_eq!(
+            requests[1].path,
+            "/repos/feenkcom/gtoolkit/releases/assets/3"
+        );
+    }
+
+    #[tokio::test]
+    async fn uploads_an_asset() {
+        let stub = HttpStub::start(|_| StubResponse::new(201)).await;
+
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("GlamorousToolkit.zip");
+        std::fs::write(&file, b"package").unwrap();
+
+        let github = release(&stub);
+        let info: GitHubReleaseInfo =
+            serde_json::from_value(release_json(&stub.url(), serde_json::json!([]))).unwrap();
+        github
+            .upload_asset(&info, "GlamorousToolkit v1.zip", &file)
+            .await
+            .unwrap();
+
+        let requests = stub.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(
+            requests[0].path,
+            "/repos/feenkcom/gtoolkit/releases/1/assets?name=GlamorousToolkit+v1.zip"
+        );
+        assert_eq!(
+            requests[0].headers["content-type"],
+            "application/octet-stream"
+        );
+        assert_eq!(requests[0].body, b"package");
+    }
+
+    #[tokio::test]
+    async fn fails_with_the_response() {
+        let stub = HttpStub::start(|_| StubResponse::new(422)).await;
+
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("GlamorousToolkit.zip");
+        std::fs::write(&file, b"package").unwrap();
+
+        let github = release(&stub);
+        let info: GitHubReleaseInfo =
+            serde_json::from_value(release_json(&stub.url(), serde_json::json!([]))).unwrap();
+        let error = github
+            .upload_asset(&info, "GlamorousToolkit.zip", &file)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            InstallerError::PublishRequestFailed(_, reqwest::StatusCode::UNPROCESSABLE_ENTITY, _)
+        ));
+    }
+}
@@ -1,13 +1,19 @@
 use crate::{
-    ExecutableSmalltalk, Result, Smalltalk, SmalltalkCommand, SmalltalkExpression,
-    SmalltalkExpressionBuilder, TestOptions,
+    ExecutableSmalltalk, LoadedRepository, Result, Smalltalk, SmalltalkCommand,
+    SmalltalkExpression, SmalltalkExpressionBuilder, TestOptions, TestablePackage,
 };
 use feenk_releaser::{Version, VersionBump};
+use std::path::Path;
+
+/// A file in the workspace in which the image writes the list of packages with examples and tests
+const TESTABLE_PACKAGES_FILE: &str = "testable-packages.tsv";
+/// A file in the workspace in which the image writes the list of loaded repositories
+const LOADED_REPOSITORIES_FILE: &str = "loaded-repositories.tsv";
 
 pub trait GToolkit {
     fn get_gtoolkit_version(&self) -> Result<Version>;
     fn get_app_version(&self) -> Result<Version>;
-    fn print_new_commits(&self) -> Result<()>;
+    fn get_new_commits(&self) -> Result<String>;
     fn perform_setup_for_release(&self, bump: VersionBump) -> Result<()>;
     fn perform_setup_for_local_build(&self) -> Result<()>;
     fn perform_iceberg_clean_up(&self) -> Result<()>;
@@ -15,7 +21,9 @@ pub trait GToolkit {
     fn run_release_examples(&self, test_options: &TestOptions) -> Result<()>;
     fn run_release_slides(&self, test_options: &TestOptions) -> Result<()>;
     fn run_tests(&self, packages: &Vec<String>) -> Result<()>;
-    fn run_architectural_report(&self) -> Result<()>;
+    fn run_report(&self, report: &str) -> Result<()>;
+    fn list_testable_packages(&self) -> Result<Vec<TestablePackage>>;
+    fn list_loaded_repositories(&self) -> Result<Vec<LoadedRepository>>;
 }
 
 impl<'application> GToolkit for Smalltalk<'application> {
@@ -31,8 +39,8 @@ impl<'application> GToolkit for Smalltalk<'application> {
         Version::parse(version_string).map_err(|error| error.into())
     }
 
-    fn print_new_commits(&self) -> Result<()> {
-        SmalltalkCommand::new("printNewCommits").execute(&self.evaluator())
+    fn get_new_commits(&self) -> Result<String> {
+        SmalltalkCommand::new("printNewCommits").execute_with_result(&self.evaluator())
     }
 
     fn perform_setup_for_release(&self, bump: VersionBump) -> Result<()> {
@@ -114,9 +122,97 @@ impl<'application> GToolkit for Smalltalk<'application> {
             .execute(&self.evaluator())
     }
 
-    fn run_architectural_report(&self) -> Result<()> {
+    fn run_report(&self, report: &str) -> Result<()> {
         SmalltalkCommand::new("gtexportreport")
-            .arg("--report=GtGtoolkitArchitecturalReport")
+            .arg(format!("--report={}", report))
             .execute(&self.evaluator())
     }
+
+    fn list_testable_packages(&self) -> Result<Vec<TestablePackage>> {
+        let packages_file = self.workspace().join(TESTABLE_PACKAGES_FILE);
+
+        let written = SmalltalkExpressionBuilder::new()
+            .add(format!(
+                "{} asFileReference ensureDelete; writeStreamDo: [ :aStream | {} ]",
+                smalltalk_string(&packages_file),
+                "self packageOrganizer packages do: [ :eachPackage | aStream \
+                    nextPutAll: eachPackage name; tab; \
+                    print: (eachPackage methods anySatisfy: [ :eachMethod | eachMethod hasPragmaNamed: #gtExample ]); tab; \
+                    print: (eachPackage definedClasses anySatisfy: [ :eachClass | eachClass inheritsFrom: TestCase ]); lf ]"
+            ))
+            .build()
+            .execute(&self.evaluator());
+
+        let content = read_and_remove(&packages_file, written)?;
+
+        let mut packages = content
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split('\t');
+                let name = columns.next()?.trim();
+                let has_examples = columns.next()? == "true";
+                let has_tests = columns.next()? == "true";
+                Some(TestablePackage {
+                    name: name.to_string(),
+                    has_examples,
+                    has_tests,
+                })
+            })
+            .filter(|package| package.is_testable())
+            .collect::<Vec<TestablePackage>>();
+
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(packages)
+    }
+
+    fn list_loaded_repositories(&self) -> Result<Vec<LoadedRepository>> {
+        let repositories_file = self.workspace().join(LOADED_REPOSITORIES_FILE);
+
+        let written = SmalltalkExpressionBuilder::new()
+            .add(format!(
+                "{} asFileReference ensureDelete; writeStreamDo: [ :aStream | {} ]",
+                smalltalk_string(&repositories_file),
+                "IceRepository registry do: [ :eachRepository | aStream \
+                    nextPutAll: eachRepository name; tab; \
+                    nextPutAll: ([ eachRepository headCommit id ] on: Error do: [ '' ]); tab; \
+                    nextPutAll: ([ eachRepository origin url ] on: Error do: [ '' ]); lf ]"
+            ))
+            .build()
+            .execute(&self.evaluator());
+        let content = read_and_remove(&repositories_file, written)?;
+
+        let mut repositories = content
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split('\t');
+                let name = columns.next()?.trim();
+                let commit = columns.next().unwrap_or_default().trim();
+                let url = columns.next().unwrap_or_default().trim();
+                Some(LoadedRepository {
+                    name: name.to_string(),
+                    commit: Some(commit.to_string()).filter(|commit| !commit.is_empty()),
+                    url: Some(url.to_string()).filter(|url| !url.is_empty()),
+                })
+            })
+            .filter(|repository| !repository.name.is_empty())
+            .collect::<Vec<LoadedRepository>>();
+
+        repositories.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(repositories)
+    }
+}
+
+/// Quotes a path as a Smalltalk string literal, doubling the quotes within it
+fn smalltalk_string(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', "''"))
+}
+
+/// Reads a file written by the image and removes it from the workspace,
+/// also when the image failed to write it completely
+fn read_and_remove(file: &Path, written: Result<()>) -> Result<String> {
+    let content = written.and_then(|_| Ok(std::fs::read_to_string(file)?));
+    if file.exists() {
+        std::fs::remove_file(file)?;
+    }
+    content
 }
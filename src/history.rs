@@ -0,0 +1,137 @@
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::{Application, InstallerError, JUnitTestSuite, Result, TestOutcome};
+
+/// A default name of the test history file within the workspace
+pub const TEST_HISTORY_FILE: &str = "test-history.jsonl";
+
+/// An append-only file with one test run per line, serialized as json
+#[derive(Debug, Clone)]
+pub struct TestHistory {
+    file: PathBuf,
+}
+
+/// The outcome of every example and test of a single `test` invocation
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestRun {
+    pub date: DateTime<Utc>,
+    /// A build identifier of the CI server, read from the `BUILD_TAG` environment variable
+    #[serde(default)]
+    pub build: Option<String>,
+    pub image_version: String,
+    pub app_version: String,
+    pub results: Vec<TestCaseResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestCaseResult {
+    pub package: String,
+    pub name: String,
+    pub outcome: TestOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl TestHistory {
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self { file: file.into() }
+    }
+
+    /// Returns the history stored in a given file, or in the workspace if no file is specified
+    pub fn for_application(application: &Application, file: Option<&Path>) -> Self {
+        match file {
+            Some(file) => Self::new(file),
+            None => Self::new(application.workspace().join(TEST_HISTORY_FILE)),
+        }
+    }
+
+    pub fn append(&self, run: &TestRun) -> Result<()> {
+        if let Some(directory) = self.file.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.file)?;
+        let line = serde_json::to_string(run)
+            .map_err(|error| InstallerError::TestHistoryError(self.file.clone(), error))?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    /// Returns all recorded runs, the oldest first. An absent history has no runs.
+    pub fn runs(&self) -> Result<Vec<TestRun>> {
+        if !self.file.exists() {
+            return Ok(vec![]);
+        }
+
+        let reader = BufReader::new(std::fs::File::open(&self.file)?);
+        let mut runs = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            runs.push(
+                serde_json::from_str(&line)
+                    .map_err(|error| InstallerError::TestHistoryError(self.file.clone(), error))?,
+            );
+        }
+        runs.sort_by_key(|run: &TestRun| run.date);
+        Ok(runs)
+    }
+}
+
+impl TestRun {
+    pub fn new<'a>(
+        application: &Application,
+        date: DateTime<Utc>,
+        suites: impl IntoIterator<Item = &'a JUnitTestSuite>,
+    ) -> Self {
+        let results = suites
+            .into_iter()
+            .flat_map(|suite| {
+                suite.test_cases().iter().map(|test_case| TestCaseResult {
+                    package: suite.name().to_string(),
+                    name: test_case.full_name(),
+                    outcome: test_case.outcome(),
+                    message: test_case
+                        .failure_message()
+                        .map(|message| message.to_string()),
+                })
+            })
+            .collect();
+
+        Self {
+            date,
+            build: std::env::var("BUILD_TAG").ok(),
+            image_version: application.image_version().to_string(),
+            app_version: application.app_version().to_string(),
+            results,
+        }
+    }
+
+    /// Returns the result of the example or test with a given full name (`Class>>#selector`)
+    pub fn result_of(&self, name: &str) -> Option<&TestCaseResult> {
+        self.results.iter().find(|result| result.name == name)
+    }
+
+    /// Returns a short description of the run to identify it in the query results
+    pub fn description(&self) -> String {
+        let mut description = format!(
+            "{} image v{} app v{}",
+            self.date.format("%Y-%m-%d %H:%M"),
+            &self.image_version,
+            &self.app_version
+        );
+        if let Some(ref build) = self.build {
+            description.push_str(&format!(" ({})", build));
+        }
+        description
+    }
+}
@@ -0,0 +1,139 @@
+//! A minimal HTTP/1.1 server for testing the publishing clients against canned responses
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use url::Url;
+
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+    pub method: String,
+    /// The path with the query
+    pub path: String,
+    /// Header names are lowercase
+    pub headers: BTreeMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StubResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl StubResponse {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn json(self, body: serde_json::Value) -> Self {
+        let mut response = self.header("Content-Type", "application/json");
+        response.body = body.to_string().into_bytes();
+        response
+    }
+}
+
+/// Answers every request with the response of a handler and records the requests
+pub struct HttpStub {
+    url: Url,
+    requests: Arc<Mutex<Vec<StubRequest>>>,
+}
+
+impl HttpStub {
+    pub async fn start(
+        handler: impl Fn(&StubRequest) -> StubResponse + Send + Sync + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let handler = Arc::new(handler);
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let request = Self::read_request(&mut stream).await;
+                let response = handler(&request);
+                recorded.lock().unwrap().push(request);
+
+                let mut head = format!("HTTP/1.1 {} Stub\r\n", response.status);
+                for (name, value) in &response.headers {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                head.push_str(&format!(
+                    "Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    response.body.len()
+                ));
+                stream.write_all(head.as_bytes()).await.unwrap();
+                stream.write_all(&response.body).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    pub fn requests(&self) -> Vec<StubRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> StubRequest {
+        let mut data = vec![];
+        let mut buffer = [0u8; 8192];
+        let head_end = loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            data.extend_from_slice(&buffer[..read]);
+            if let Some(position) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+                break position;
+            }
+            assert!(
+                read > 0,
+                "The connection was closed before the end of headers"
+            );
+        };
+
+        let head = String::from_utf8_lossy(&data[..head_end]).to_string();
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next().unwrap().split(' ');
+        let method = request_line.next().unwrap().to_string();
+        let path = request_line.next().unwrap().to_string();
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect::<BTreeMap<String, String>>();
+
+        let content_length = headers
+            .get("content-length")
+            .map(|length| length.parse::<usize>().unwrap())
+            .unwrap_or_default();
+        let mut body = data[head_end + 4..].to_vec();
+        while body.len() < content_length {
+            let read = stream.read(&mut buffer).await.unwrap();
+            assert!(read > 0, "The connection was closed before the end of body");
+            body.extend_from_slice(&buffer[..read]);
+        }
+
+        StubRequest {
+            method,
+            path,
+            headers,
+            body,
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{InstallerError, Result};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Test results read from one or more JUnit XML files exported by the image
+#[derive(Debug, Clone, Default)]
+pub struct JUnitReport {
+    suites: Vec<JUnitTestSuite>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct JUnitTestSuites {
+    #[serde(rename = "testsuite", default)]
+    suites: Vec<JUnitTestSuite>,
+}
+
+/// Usually corresponds to a package that was tested
+#[derive(Deserialize, Debug, Clone)]
+pub struct JUnitTestSuite {
+    name: String,
+    #[serde(default)]
+    time: Option<f64>,
+    #[serde(rename = "testcase", default)]
+    test_cases: Vec<JUnitTestCase>,
+}
+
+/// Usually corresponds to an example or a test method
+#[derive(Deserialize, Debug, Clone)]
+pub struct JUnitTestCase {
+    #[serde(default)]
+    classname: String,
+    name: String,
+    #[serde(default)]
+    time: Option<f64>,
+    failure: Option<JUnitMessage>,
+    error: Option<JUnitMessage>,
+    skipped: Option<JUnitMessage>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct JUnitMessage {
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Errored,
+    Skipped,
+}
+
+impl Display for TestOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::Errored => "errored",
+            Self::Skipped => "skipped",
+        })
+    }
+}
+
+impl TestOutcome {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Self::Failed | Self::Errored)
+    }
+}
+
+impl JUnitReport {
+    pub fn new() -> Self {
+        Self { suites: vec![] }
+    }
+
+    /// Reads all JUnit XML files in the directory that were modified after a given moment in time.
+    /// XML files that do not contain a test suite are ignored, malformed ones are reported and skipped
+    /// so that the results of the tests that already ran are not lost.
+    pub fn read_from_directory_since(
+        directory: impl AsRef<Path>,
+        since: SystemTime,
+    ) -> Result<Self> {
+        let mut report = Self::new();
+
+        for entry in std::fs::read_dir(directory.as_ref())? {
+            let path = entry?.path();
+            if !path.is_file()
+                || path.extension().and_then(|extension| extension.to_str()) != Some("xml")
+            {
+                continue;
+            }
+            if std::fs::metadata(&path)?.modified()? < since {
+                continue;
+            }
+            if let Err(error) = report.read_file(&path) {
+                match std::error::Error::source(&error) {
+                    Some(source) => eprintln!("Warning: {}, skipping it: {}", error, source),
+                    None => eprintln!("Warning: {}, skipping it", error),
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn read_file(&mut self, file: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(file)?;
+
+        if content.contains("<testsuites") {
+            let suites: JUnitTestSuites = serde_xml_rs::from_str(&content)
+                .map_err(|error| InstallerError::JUnitParseError(file.to_path_buf(), error))?;
+            self.suites.extend(suites.suites);
+        } else if content.contains("<testsuite") {
+            let suite: JUnitTestSuite = serde_xml_rs::from_str(&content)
+                .map_err(|error| InstallerError::JUnitParseError(file.to_path_buf(), error))?;
+            self.suites.push(suite);
+        }
+
+        Ok(())
+    }
+
+    pub fn suites(&self) -> &[JUnitTestSuite] {
+        self.suites.as_slice()
+    }
+}
+
+impl JUnitTestSuite {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn test_cases(&self) -> &[JUnitTestCase] {
+        self.test_cases.as_slice()
+    }
+
+    /// Returns the time of the whole suite, or the sum of its test cases if the suite is not timed
+    pub fn duration(&self) -> Duration {
+        match self.time {
+            Some(time) => seconds_to_duration(time),
+            None => self.test_cases.iter().map(|each| each.duration()).sum(),
+        }
+    }
+}
+
+impl JUnitTestCase {
+    pub fn class_name(&self) -> &str {
+        self.classname.as_str()
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Returns a name of the test case in a form of `Class>>#selector`
+    pub fn full_name(&self) -> String {
+        if self.classname.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}>>#{}", &self.classname, &self.name)
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.time.map_or(Duration::ZERO, seconds_to_duration)
+    }
+
+    pub fn outcome(&self) -> TestOutcome {
+        if self.error.is_some() {
+            TestOutcome::Errored
+        } else if self.failure.is_some() {
+            TestOutcome::Failed
+        } else if self.skipped.is_some() {
+            TestOutcome::Skipped
+        } else {
+            TestOutcome::Passed
+        }
+    }
+
+    /// Returns the message of a failure or an error if there is one
+    pub fn failure_message(&self) -> Option<&str> {
+        self.error
+            .as_ref()
+            .or(self.failure.as_ref())
+            .and_then(|failure| failure.message.as_deref())
+    }
+}
+
+fn seconds_to_duration(seconds: f64) -> Duration {
+    if seconds.is_finite() && seconds > 0.0 {
+        Duration::from_secs_f64(seconds)
+    } else {
+        Duration::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filetime::FileTime;
+
+    const SUITES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+    <testsuite name="Brick" time="1.5">
+        <testcase classname="BrickExamples" name="button" time="0.5"/>
+        <testcase classname="BrickExamples" name="label" time="1">
+            <failure message="Assertion failed"/>
+        </testcase>
+    </testsuite>
+</testsuites>"#;
+
+    const SUITE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="Lepiter">
+    <testcase classname="LeExamples" name="page" time="0.25"/>
+    <testcase classname="LeExamples" name="database" time="0.75">
+        <error message="Error"/>
+    </testcase>
+    <testcase classname="LeExamples" name="snippet" time="-1">
+        <skipped/>
+    </testcase>
+</testsuite>"#;
+
+    #[test]
+    fn reads_the_durations_in_seconds() {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::write(directory.path().join("Brick.xml"), SUITES).unwrap();
+        std::fs::write(directory.path().join("Lepiter.xml"), SUITE).unwrap();
+
+        let report =
+            JUnitReport::read_from_directory_since(directory.path(), SystemTime::UNIX_EPOCH)
+                .unwrap();
+        let mut suites = report.suites().to_vec();
+        suites.sort_by(|a, b| a.name().cmp(b.name()));
+        assert_eq!(suites.len(), 2);
+
+        let brick = &suites[0];
+        assert_eq!(brick.name(), "Brick");
+        assert_eq!(brick.duration(), Duration::from_millis(1500));
+        assert_eq!(
+            brick
+                .test_cases()
+                .iter()
+                .map(|test_case| (
+                    test_case.full_name(),
+                    test_case.duration(),
+                    test_case.outcome()
+                ))
+                .collect::<Vec<(String, Duration, TestOutcome)>>(),
+            vec![
+                (
+                    "BrickExamples>>#button".to_string(),
+                    Duration::from_millis(500),
+                    TestOutcome::Passed
+                ),
+                (
+                    "BrickExamples>>#label".to_string(),
+                    Duration::from_secs(1),
+                    TestOutcome::Failed
+                ),
+            ]
+        );
+        assert_eq!(
+            brick.test_cases()[1].failure_message(),
+            Some("Assertion failed")
+        );
+
+        // a suite without time takes as long as its test cases, negative times are ignored
+        let lepiter = &suites[1];
+        assert_eq!(lepiter.duration(), Duration::from_secs(1));
+        assert_eq!(
+            lepiter
+                .test_cases()
+                .iter()
+                .map(|test_case| test_case.outcome())
+                .collect::<Vec<TestOutcome>>(),
+            vec![
+                TestOutcome::Passed,
+                TestOutcome::Errored,
+                TestOutcome::Skipped
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_malformed_and_outdated_files() {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::write(directory.path().join("Brick.xml"), SUITES).unwrap();
+        std::fs::write(
+            directory.path().join("Broken.xml"),
+            "<testsuite name=\"Broken\"><testcase",
+        )
+        .unwrap();
+        std::fs::write(directory.path().join("pom.xml"), "<project/>").unwrap();
+        std::fs::write(directory.path().join("Lepiter.xml"), SUITE).unwrap();
+        filetime::set_file_mtime(
+            directory.path().join("Lepiter.xml"),
+            FileTime::from_unix_time(1000, 0),
+        )
+        .unwrap();
+
+        let since = SystemTime::UNIX_EPOCH + Duration::from_secs(2000);
+        let report = JUnitReport::read_from_directory_since(directory.path(), since).unwrap();
+        assert_eq!(
+            report
+                .suites()
+                .iter()
+                .map(|suite| suite.name())
+                .collect::<Vec<&str>>(),
+            vec!["Brick"]
+        );
+    }
+}
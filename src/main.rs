@@ -4,22 +4,48 @@
 extern crate serde_derive;
 
 mod application;
+mod archive;
 mod create;
+mod debian;
 mod error;
+mod github_release;
 mod gtoolkit;
+mod history;
+#[cfg(test)]
+mod http_stub;
+mod junit;
+mod manifest;
 mod moving;
 mod options;
+mod release_notes;
+mod s3;
+mod sbom;
 mod seed;
+mod signature;
 mod smalltalk;
+mod snapshot;
+mod template;
 mod tools;
 mod version;
 
 pub use application::*;
+pub use archive::*;
+pub use debian::*;
 pub use error::*;
+pub use github_release::*;
 pub use gtoolkit::*;
+pub use history::*;
+pub use junit::*;
+pub use manifest::*;
 pub use moving::*;
+pub use release_notes::*;
+pub use s3::*;
+pub use sbom::*;
 pub use seed::*;
+pub use signature::*;
 pub use smalltalk::*;
+pub use snapshot::*;
+pub use template::*;
 pub use tools::*;
 pub use version::*;
 
@@ -45,6 +71,7 @@ async fn run() -> Result<()> {
     let options: AppOptions = AppOptions::parse();
 
     let mut application = Application::for_workspace(options.workspace()).await?;
+    let automatic_snapshots = !options.no_snapshot();
     application.set_verbose(options.verbose());
     if let Some(ref app_cli_bin) = options.app_cli_binary {
         application.set_app_cli_binary(app_cli_bin)?;
@@ -62,11 +89,23 @@ async fn run() -> Result<()> {
                 .await?;
         }
         SubCommand::Setup(setup_options) => {
+            if automatic_snapshots && matches!(setup_options.target, SetupTarget::Release) {
+                Snapshotter::new().snapshot_before(&application, "setup")?;
+            }
             Setup::new().setup(&mut application, &setup_options).await?;
         }
         SubCommand::Test(test_options) => {
             Tester::new().test(&application, &test_options).await?;
         }
+        SubCommand::TestHistory(history_options) => {
+            Historian::new().query(&application, &history_options)?;
+        }
+        SubCommand::ListPackages(list_options) => {
+            let packages = Tester::new()
+                .list_packages(&application, &list_options)
+                .await?;
+            packages.iter().for_each(|package| println!("{}", package));
+        }
         SubCommand::LocalBuild(local_build) => {
             let mut setup_options = SetupOptions::new();
             setup_options.setup_target(SetupTarget::LocalBuild);
@@ -92,12 +131,24 @@ async fn run() -> Result<()> {
             Copier::new().copy(&mut application, &copy_options).await?;
         }
         SubCommand::RenameTo(rename_options) => {
+            rename_options.validate()?;
+            if automatic_snapshots {
+                Snapshotter::new().snapshot_before(&application, "rename-to")?;
+            }
             Renamer::new()
                 .rename(&mut application, &rename_options)
                 .await?;
         }
-        SubCommand::CleanUp => {
-            Cleaner::new().clean(&application).await?;
+        SubCommand::CleanUp(clean_up_options) => {
+            if automatic_snapshots && clean_up_options.modifies_image() {
+                Snapshotter::new().snapshot_before(&application, "clean-up")?;
+            }
+            Cleaner::new()
+                .clean(&application, &clean_up_options)
+                .await?;
+        }
+        SubCommand::Snapshot(snapshot_options) => {
+            Snapshotter::new().snapshot(&mut application, &snapshot_options)?;
         }
         SubCommand::Start(start_options) => {
             Starter::new().start(&application, &start_options).await?;
@@ -113,10 +164,23 @@ async fn run() -> Result<()> {
                 .await?;
         }
         SubCommand::PackageRelease(release_options) => {
-            let package = Release::new()
+            let packages = Release::new()
                 .package(&application, &release_options)
                 .await?;
-            println!("{}", package.display())
+            for package in packages {
+                println!("{}", package.display())
+            }
+        }
+        SubCommand::VerifyPackage(verify_options) => {
+            PackageVerifier::new().verify(&application, &verify_options)?;
+        }
+        SubCommand::VerifySignature(signature_options) => {
+            PackageVerifier::new().verify_signature(&signature_options)?;
+        }
+        SubCommand::Publish(publish_options) => {
+            Publisher::new()
+                .publish(&application, &publish_options)
+                .await?;
         }
         SubCommand::RunReleaser(releaser_options) => {
             Release::new()
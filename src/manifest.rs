@@ -0,0 +1,453 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use clap::ArgEnum;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::{Application, CustomerLevel, ImageSeed, InstallerError, PlatformOS, Result};
+
+/// An aggregate checksums file next to the release packages, in the format of `sha256sum`
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+/// A manifest embedded in the root of a tentative package
+pub const TENTATIVE_MANIFEST_FILE: &str = "tentative-manifest.json";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+pub enum ManifestFormat {
+    Json,
+    Yaml,
+}
+
+impl ManifestFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+        }
+    }
+}
+
+/// Describes the content of a release package and the versions it was built from
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReleaseManifest {
+    pub package: String,
+    pub size: u64,
+    pub sha256: String,
+    pub image_version: String,
+    pub app_version: String,
+    pub target: String,
+    pub customer_level: CustomerLevel,
+    pub seed_url: Option<String>,
+    pub files: Vec<ManifestFile>,
+}
+
+/// Describes the content of a tentative package so that it can be verified before unpackaging
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TentativeManifest {
+    pub image_version: String,
+    pub app_version: String,
+    pub files: Vec<ManifestFile>,
+}
+
+/// A file within the release package, with a path relative to the root of the package
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl ReleaseManifest {
+    pub fn new(
+        application: &Application,
+        target: PlatformOS,
+        customer_level: CustomerLevel,
+        package: impl AsRef<Path>,
+        files: Vec<ManifestFile>,
+    ) -> Result<Self> {
+        let package = package.as_ref();
+        let package_name = package
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| InstallerError::FailedToReadFileName(package.to_path_buf()))?
+            .to_string();
+
+        let seed_url = match application.image_seed() {
+            ImageSeed::Url(url) => Some(url.to_string()),
+            _ => None,
+        };
+
+        Ok(Self {
+            package: package_name,
+            size: std::fs::metadata(package)?.len(),
+            sha256: sha256_of_file(package)?,
+            image_version: application.image_version().to_string(),
+            app_version: application.app_version().to_string(),
+            target: target.as_str().to_string(),
+            customer_level,
+            seed_url,
+            files,
+        })
+    }
+
+    /// Returns the path of the manifest that describes a given package: `<package>.manifest.<format>`
+    pub fn manifest_path(package: impl AsRef<Path>, format: ManifestFormat) -> PathBuf {
+        let package = package.as_ref();
+        let mut file_name = package.file_name().unwrap_or_default().to_os_string();
+        file_name.push(format!(".manifest.{}", format.extension()));
+        package.with_file_name(file_name)
+    }
+
+    pub fn write(&self, package: impl AsRef<Path>, format: ManifestFormat) -> Result<PathBuf> {
+        let manifest_path = Self::manifest_path(package, format);
+        let content = match format {
+            ManifestFormat::Json => serde_json::to_string_pretty(self).map_err(|error| {
+                InstallerError::ManifestSerializationError(manifest_path.clone(), error)
+            })?,
+            ManifestFormat::Yaml => serde_yaml::to_string(self)?,
+        };
+        std::fs::write(&manifest_path, content)?;
+        Ok(manifest_path)
+    }
+
+    pub fn read(manifest: impl AsRef<Path>, format: ManifestFormat) -> Result<Self> {
+        let manifest = manifest.as_ref();
+        let content = std::fs::read_to_string(manifest)?;
+        Ok(match format {
+            ManifestFormat::Json => serde_json::from_str(&content).map_err(|error| {
+                InstallerError::ManifestDeserializationError(manifest.to_path_buf(), error)
+            })?,
+            ManifestFormat::Yaml => serde_yaml::from_str(&content)?,
+        })
+    }
+
+    /// Returns the differences between a given package and the size and checksum it is described with
+    pub fn package_problems(&self, package: impl AsRef<Path>) -> Result<Vec<String>> {
+        let package = package.as_ref();
+        let size = std::fs::metadata(package)?.len();
+
+        let mut problems = vec![];
+        if size != self.size {
+            problems.push(format!(
+                "{} has size {}, expected {}",
+                &self.package, size, self.size
+            ));
+        } else if sha256_of_file(package)? != self.sha256 {
+            problems.push(format!("{} has a wrong checksum", &self.package));
+        }
+        Ok(problems)
+    }
+}
+
+impl TentativeManifest {
+    /// Creates a manifest of the files and folders that are packaged into the root of the tentative package
+    pub fn new(application: &Application, entries: &[PathBuf]) -> Result<Self> {
+        let mut files = vec![];
+        for entry in entries {
+            files.extend(ManifestFile::collect(entry, "")?);
+        }
+
+        Ok(Self {
+            image_version: application.image_version().to_string(),
+            app_version: application.app_version().to_string(),
+            files,
+        })
+    }
+
+    pub fn write(&self, file: impl AsRef<Path>) -> Result<PathBuf> {
+        let file = file.as_ref();
+        let content = serde_json::to_string_pretty(self).map_err(|error| {
+            InstallerError::ManifestSerializationError(file.to_path_buf(), error)
+        })?;
+        std::fs::write(file, content)?;
+        Ok(file.to_path_buf())
+    }
+
+    /// Checks that every file listed in the embedded manifest is in the tentative package
+    /// and has the expected size and checksum
+    pub fn verify_archive(archive: impl AsRef<Path>) -> Result<Self> {
+        let archive = archive.as_ref();
+        let (manifest, problems) = Self::archive_problems(archive)?;
+
+        if problems.is_empty() {
+            Ok(manifest)
+        } else {
+            InstallerError::TentativePackageCorrupted(archive.to_path_buf(), problems.join("\n"))
+                .into()
+        }
+    }
+
+    /// Reads the embedded manifest of a tentative package and returns it together with
+    /// the files that are missing in the package or differ in their size or checksum
+    pub fn archive_problems(archive: impl AsRef<Path>) -> Result<(Self, Vec<String>)> {
+        let archive = archive.as_ref();
+        let zip_error = |error| InstallerError::ArchiveReadError(archive.to_path_buf(), error);
+
+        let mut zip = zip::ZipArchive::new(File::open(archive)?).map_err(zip_error)?;
+
+        let manifest: Self = match zip.by_name(TENTATIVE_MANIFEST_FILE) {
+            Ok(manifest_file) => serde_json::from_reader(manifest_file).map_err(|error| {
+                InstallerError::TentativeManifestError(archive.to_path_buf(), error)
+            })?,
+            Err(zip::result::ZipError::FileNotFound) => {
+                return InstallerError::TentativeManifestNotFound(archive.to_path_buf()).into();
+            }
+            Err(error) => return Err(zip_error(error)),
+        };
+
+        let mut problems = vec![];
+        for file in &manifest.files {
+            match zip.by_name(&file.path) {
+                Ok(entry) => {
+                    if entry.size() != file.size {
+                        problems.push(format!(
+                            "{} has size {}, expected {}",
+                            &file.path,
+                            entry.size(),
+                            file.size
+                        ));
+                    } else if sha256_of_reader(entry)? != file.sha256 {
+                        problems.push(format!("{} has a wrong checksum", &file.path));
+                    }
+                }
+                Err(zip::result::ZipError::FileNotFound) => {
+                    problems.push(format!("{} is missing", &file.path));
+                }
+                Err(error) => return Err(zip_error(error)),
+            }
+        }
+
+        Ok((manifest, problems))
+    }
+}
+
+impl ManifestFile {
+    /// Collects all files within a given file or folder that will be packaged into a given directory of the archive.
+    /// Symlinks are not followed and are not listed, as tar archives store them as links
+    pub fn collect(
+        entry: impl AsRef<Path>,
+        archive_directory: impl AsRef<Path>,
+    ) -> Result<Vec<Self>> {
+        let entry = entry.as_ref();
+        let entry_name = entry
+            .file_name()
+            .ok_or_else(|| InstallerError::FailedToReadFileName(entry.to_path_buf()))?;
+        let entry_root = entry.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut files = vec![];
+        for each in WalkDir::new(entry).follow_links(false).sort_by_file_name() {
+            let each = each.map_err(std::io::Error::from)?;
+            if !each.file_type().is_file() {
+                continue;
+            }
+
+            let relative_path = each
+                .path()
+                .strip_prefix(entry_root)
+                .unwrap_or_else(|_| Path::new(entry_name));
+            let archive_path = archive_directory
+                .as_ref()
+                .join(relative_path)
+                .iter()
+                .map(|component| component.to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+                .join("/");
+
+            files.push(Self {
+                path: archive_path,
+                size: each.metadata().map_err(std::io::Error::from)?.len(),
+                sha256: sha256_of_file(each.path())?,
+            });
+        }
+        Ok(files)
+    }
+
+    /// Lists the files of an already built zip-based package, such as an .apk,
+    /// with the checksums of their content as stored in the package
+    pub fn collect_archive(archive: impl AsRef<Path>) -> Result<Vec<Self>> {
+        let archive = archive.as_ref();
+        let zip_error = |error| InstallerError::ArchiveReadError(archive.to_path_buf(), error);
+
+        let mut zip = zip::ZipArchive::new(File::open(archive)?).map_err(zip_error)?;
+        let mut files = vec![];
+        for index in 0..zip.len() {
+            let entry = zip.by_index(index).map_err(zip_error)?;
+            if !entry.is_file() {
+                continue;
+            }
+            files.push(Self {
+                path: entry.name().to_string(),
+                size: entry.size(),
+                sha256: sha256_of_reader(entry)?,
+            });
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+}
+
+/// An aggregate of package checksums, one `<sha256>  <file name>` per line
+pub struct Checksums {
+    file: PathBuf,
+}
+
+impl Checksums {
+    /// Returns the checksums file in the directory of a given package
+    pub fn for_package(package: impl AsRef<Path>) -> Self {
+        let directory = package
+            .as_ref()
+            .parent()
+            .map(|directory| directory.to_path_buf())
+            .unwrap_or_default();
+        Self {
+            file: directory.join(CHECKSUMS_FILE),
+        }
+    }
+
+    /// Adds the checksum of the package to the checksums file,
+    /// replacing the previous checksum of a package with the same name
+    pub fn add(&self, manifest: &ReleaseManifest) -> Result<PathBuf> {
+        let mut checksums: BTreeMap<String, String> = BTreeMap::new();
+
+        if self.file.exists() {
+            for line in std::fs::read_to_string(&self.file)?.lines() {
+                if let Some((checksum, file_name)) = line.split_once("  ") {
+                    checksums.insert(file_name.to_string(), checksum.to_string());
+                }
+            }
+        }
+        checksums.insert(manifest.package.clone(), manifest.sha256.clone());
+
+        let content = checksums
+            .iter()
+            .map(|(file_name, checksum)| format!("{}  {}\n", checksum, file_name))
+            .collect::<String>();
+        std::fs::write(&self.file, content)?;
+        Ok(self.file.clone())
+    }
+}
+
+pub fn sha256_of_file(file: impl AsRef<Path>) -> Result<String> {
+    sha256_of_reader(BufReader::new(File::open(file.as_ref())?))
+}
+
+pub fn sha256_of_reader(mut reader: impl Read) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{Reproducible, TarCompression, ToReproducibleZip, ToTar};
+    use file_matcher::FolderNamed;
+    use flate2::read::GzDecoder;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    const LIBRARY: &[u8] = b"library";
+
+    /// Creates an App folder with a library and a versioned symlink to it, like the libraries of the Linux App
+    fn create_app(directory: &Path) -> PathBuf {
+        let app = directory.join("app");
+        std::fs::create_dir_all(app.join("lib")).unwrap();
+        std::fs::write(app.join("lib").join("libGlamorous.so"), LIBRARY).unwrap();
+        std::os::unix::fs::symlink("libGlamorous.so", app.join("lib").join("libGlamorous.so.1"))
+            .unwrap();
+        app
+    }
+
+    fn library_file(path: &str) -> ManifestFile {
+        ManifestFile {
+            path: path.to_string(),
+            size: LIBRARY.len() as u64,
+            sha256: sha256_of_reader(LIBRARY).unwrap(),
+        }
+    }
+
+    #[test]
+    fn manifest_matches_a_tar_with_symlinks() {
+        let directory = tempfile::tempdir().unwrap();
+        let app = create_app(directory.path());
+
+        let archive = ToTar::new(directory.path().join("app.tar.gz"), TarCompression::Gzip)
+            .one_entry(FolderNamed::exact("app").within(directory.path()))
+            .tar()
+            .unwrap();
+
+        let mut tar = tar::Archive::new(GzDecoder::new(File::open(archive).unwrap()));
+        let mut archived_files = vec![];
+        for entry in tar.entries().unwrap() {
+            let entry = entry.unwrap();
+            if entry.header().entry_type().is_file() {
+                archived_files.push(ManifestFile {
+                    path: entry.path().unwrap().to_string_lossy().to_string(),
+                    size: entry.size(),
+                    sha256: sha256_of_reader(entry).unwrap(),
+                });
+            }
+        }
+
+        let files = ManifestFile::collect(&app, "").unwrap();
+        assert_eq!(files, vec![library_file("app/lib/libGlamorous.so")]);
+        assert_eq!(files, archived_files);
+    }
+
+    #[test]
+    fn manifest_matches_a_reproducible_zip_with_symlinks() {
+        let directory = tempfile::tempdir().unwrap();
+        let app = create_app(directory.path());
+
+        let archive = ToReproducibleZip::new(
+            directory.path().join("app.zip"),
+            Reproducible::from_env().unwrap(),
+        )
+        .path(&app)
+        .zip()
+        .unwrap();
+
+        assert_eq!(
+            ManifestFile::collect_archive(archive).unwrap(),
+            ManifestFile::collect(&app, "").unwrap()
+        );
+    }
+
+    #[test]
+    fn manifest_lists_the_content_of_symlinks_stored_in_a_zip() {
+        let directory = tempfile::tempdir().unwrap();
+        let app = create_app(directory.path());
+
+        // zip packages of the App store the content of the symlinks as regular files
+        let archive = directory.path().join("app.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        for entry in WalkDir::new(&app).follow_links(true).sort_by_file_name() {
+            let entry = entry.unwrap();
+            if entry.file_type().is_file() {
+                let name = entry.path().strip_prefix(directory.path()).unwrap();
+                zip.start_file(name.to_string_lossy(), SimpleFileOptions::default())
+                    .unwrap();
+                zip.write_all(&std::fs::read(entry.path()).unwrap())
+                    .unwrap();
+            }
+        }
+        zip.finish().unwrap();
+
+        assert_eq!(
+            ManifestFile::collect_archive(archive).unwrap(),
+            vec![
+                library_file("app/lib/libGlamorous.so"),
+                library_file("app/lib/libGlamorous.so.1"),
+            ]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::{InstallerError, Result};
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
@@ -15,15 +15,22 @@ impl FileToMove {
         }
     }
 
+    /// Moves the file into the destination folder, or to the destination path if it is not a folder.
+    /// Symlinks are moved as they are, permissions and timestamps are preserved
     pub async fn move_file(&self) -> Result<()> {
-        let file_name = self.file.file_name().unwrap().to_str().unwrap();
-
-        if self.destination.is_dir() {
-            tokio::fs::copy(&self.file, &self.destination.join(file_name)).await?;
+        let target = if self.destination.is_dir() {
+            let file_name = self
+                .file
+                .file_name()
+                .ok_or_else(|| InstallerError::FailedToReadFileName(self.file.clone()))?;
+            self.destination.join(file_name)
         } else {
-            tokio::fs::copy(&self.file, &self.destination).await?;
-        }
-        tokio::fs::remove_file(&self.file).await?;
+            self.destination.clone()
+        };
+
+        let file = self.file.clone();
+        tokio::task::spawn_blocking(move || super::move_file_preserving_metadata(&file, &target))
+            .await??;
         Ok(())
     }
 }
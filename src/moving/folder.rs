@@ -1,5 +1,10 @@
 use crate::Result;
-use std::path::PathBuf;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Folders with more content than this show the progress when moved file by file
+const PROGRESS_THRESHOLD: u64 = 100 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct FolderToMove {
@@ -15,44 +20,240 @@ impl FolderToMove {
         }
     }
 
+    /// Moves the folder to the destination path, merging it with an existing folder.
+    /// The folder is renamed when possible, otherwise its content is moved file by file,
+    /// preserving symlinks, permissions and timestamps, and the original folder is removed
     pub async fn move_folder(&self) -> Result<()> {
-        let mut stack = Vec::new();
-        stack.push(self.folder.clone());
+        let folder = self.folder.clone();
+        let destination = self.destination.clone();
+        tokio::task::spawn_blocking(move || Self::move_folder_blocking(&folder, &destination))
+            .await?
+    }
+
+    fn move_folder_blocking(folder: &Path, destination: &Path) -> Result<()> {
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // a symlink to a folder is moved as it is, the folder it points to is left untouched
+        if std::fs::symlink_metadata(folder)?.file_type().is_symlink() {
+            super::move_file_preserving_metadata(folder, destination)?;
+            return Ok(());
+        }
 
-        let output_root = self.destination.clone();
-        let input_root = self.folder.components().count();
+        if !destination.exists() && std::fs::rename(folder, destination).is_ok() {
+            return Ok(());
+        }
+
+        Self::move_content(folder, destination, super::move_file_preserving_metadata)
+    }
 
-        while let Some(working_path) = stack.pop() {
-            // Generate a relative path
-            let src: PathBuf = working_path.components().skip(input_root).collect();
+    /// Moves the content of the folder file by file with a given function and removes the folder
+    fn move_content(
+        folder: &Path,
+        destination: &Path,
+        move_file: fn(&Path, &Path) -> Result<u64>,
+    ) -> Result<()> {
+        // contents go first, so that the folders can be removed and their metadata restored deepest first
+        let entries = WalkDir::new(folder)
+            .follow_links(false)
+            .contents_first(true)
+            .into_iter()
+            .collect::<std::result::Result<Vec<walkdir::DirEntry>, walkdir::Error>>()
+            .map_err(std::io::Error::from)?;
 
-            // Create a destination if missing
-            let dest = if src.components().count() == 0 {
-                output_root.clone()
+        let target_of = |entry: &walkdir::DirEntry| match entry.path().strip_prefix(folder) {
+            Ok(relative) if !relative.as_os_str().is_empty() => destination.join(relative),
+            _ => destination.to_path_buf(),
+        };
+
+        // moving the files out of a folder modifies it, so its metadata is read beforehand
+        // and restored once all files are moved
+        let mut size = 0;
+        let mut folders = vec![];
+        for entry in &entries {
+            let metadata = entry.metadata().map_err(std::io::Error::from)?;
+            if entry.file_type().is_dir() {
+                folders.push((target_of(entry), metadata));
             } else {
-                output_root.join(&src)
-            };
-            if tokio::fs::metadata(&dest).await.is_err() {
-                tokio::fs::create_dir_all(&dest).await?;
+                size += metadata.len();
             }
+        }
+
+        let progress = if size >= PROGRESS_THRESHOLD {
+            let progress = ProgressBar::new(size);
+            progress.set_style(
+                ProgressStyle::default_bar()
+                    .template("{msg} {wide_bar:.blue} {bytes}/{total_bytes}")
+                    .unwrap(),
+            );
+            progress.set_message(format!("Moving {}", folder.display()));
+            Some(progress)
+        } else {
+            None
+        };
 
-            let mut entries = tokio::fs::read_dir(working_path).await?;
-            while let Some(entry) = entries.next_entry().await? {
-                let path = entry.path();
-                if path.is_dir() {
-                    stack.push(path);
-                } else {
-                    match path.file_name() {
-                        Some(filename) => {
-                            let dest_path = dest.join(filename);
-                            tokio::fs::copy(&path, &dest_path).await?;
-                        }
-                        None => {}
-                    }
-                }
+        for (target, _) in folders.iter().rev() {
+            std::fs::create_dir_all(target)?;
+        }
+
+        for entry in entries.iter().filter(|entry| !entry.file_type().is_dir()) {
+            let moved = move_file(entry.path(), &target_of(entry))?;
+            if let Some(ref progress) = progress {
+                progress.inc(moved);
             }
         }
 
+        for (target, metadata) in &folders {
+            super::preserve_folder_metadata(metadata, target)?;
+        }
+
+        std::fs::remove_dir_all(folder)?;
+
+        if let Some(progress) = progress {
+            progress.finish_and_clear();
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filetime::FileTime;
+
+    fn modified(path: &Path) -> FileTime {
+        FileTime::from_last_modification_time(&std::fs::symlink_metadata(path).unwrap())
+    }
+
+    #[cfg(unix)]
+    fn mode(path: &Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[cfg(unix)]
+    fn set_mode(path: &Path, mode: u32) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    /// Creates a folder with a file, a nested folder and symlinks to a file and to a folder.
+    /// The timestamps are set once the content is created
+    #[cfg(unix)]
+    fn create_folder(folder: &Path) {
+        std::fs::create_dir_all(folder.join("nested")).unwrap();
+        std::fs::write(folder.join("Pharo.image"), b"image").unwrap();
+        std::fs::write(folder.join("nested").join("library.so"), b"library").unwrap();
+        std::os::unix::fs::symlink("../Pharo.image", folder.join("nested").join("image")).unwrap();
+        std::os::unix::fs::symlink("nested", folder.join("linked")).unwrap();
+
+        set_mode(&folder.join("nested").join("library.so"), 0o751);
+        set_mode(&folder.join("nested"), 0o750);
+
+        filetime::set_file_mtime(
+            folder.join("Pharo.image"),
+            FileTime::from_unix_time(1000, 0),
+        )
+        .unwrap();
+        filetime::set_file_mtime(
+            folder.join("nested").join("library.so"),
+            FileTime::from_unix_time(2000, 0),
+        )
+        .unwrap();
+        filetime::set_file_mtime(folder.join("nested"), FileTime::from_unix_time(3000, 0)).unwrap();
+        filetime::set_file_mtime(folder, FileTime::from_unix_time(4000, 0)).unwrap();
+    }
+
+    #[cfg(unix)]
+    fn assert_moved(folder: &Path, destination: &Path) {
+        assert!(!folder.exists());
+
+        assert_eq!(
+            std::fs::read(destination.join("Pharo.image")).unwrap(),
+            b"image"
+        );
+        assert_eq!(
+            std::fs::read_link(destination.join("nested").join("image")).unwrap(),
+            Path::new("../Pharo.image")
+        );
+        assert_eq!(
+            std::fs::read_link(destination.join("linked")).unwrap(),
+            Path::new("nested")
+        );
+
+        assert_eq!(mode(&destination.join("nested").join("library.so")), 0o751);
+        assert_eq!(mode(&destination.join("nested")), 0o750);
+
+        assert_eq!(
+            modified(&destination.join("Pharo.image")),
+            FileTime::from_unix_time(1000, 0)
+        );
+        assert_eq!(
+            modified(&destination.join("nested").join("library.so")),
+            FileTime::from_unix_time(2000, 0)
+        );
+        assert_eq!(
+            modified(&destination.join("nested")),
+            FileTime::from_unix_time(3000, 0)
+        );
+        assert_eq!(modified(destination), FileTime::from_unix_time(4000, 0));
+    }
+
+    /// Moving across filesystems copies every file and removes the original
+    #[cfg(unix)]
+    #[test]
+    fn copy_and_remove_preserves_metadata() {
+        let directory = tempfile::tempdir().unwrap();
+        let folder = directory.path().join("workspace");
+        let destination = directory.path().join("moved");
+        create_folder(&folder);
+
+        FolderToMove::move_content(&folder, &destination, super::super::copy_and_remove_file)
+            .unwrap();
+
+        assert_moved(&folder, &destination);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn merges_into_an_existing_folder() {
+        let directory = tempfile::tempdir().unwrap();
+        let folder = directory.path().join("workspace");
+        let destination = directory.path().join("moved");
+        create_folder(&folder);
+        std::fs::create_dir_all(destination.join("nested")).unwrap();
+        std::fs::write(destination.join("nested").join("kept.txt"), b"kept").unwrap();
+
+        FolderToMove::new(&folder, &destination)
+            .move_folder()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(destination.join("nested").join("kept.txt")).unwrap(),
+            b"kept"
+        );
+        assert_moved(&folder, &destination);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn moves_a_symlinked_folder_as_a_symlink() {
+        let directory = tempfile::tempdir().unwrap();
+        let folder = directory.path().join("workspace");
+        create_folder(&folder);
+        let symlink = directory.path().join("linked-workspace");
+        std::os::unix::fs::symlink(&folder, &symlink).unwrap();
+        let destination = directory.path().join("release").join("workspace");
+
+        FolderToMove::new(&symlink, &destination)
+            .move_folder()
+            .await
+            .unwrap();
+
+        assert!(std::fs::symlink_metadata(&symlink).is_err());
+        assert_eq!(std::fs::read_link(&destination).unwrap(), folder);
+        assert_eq!(std::fs::read(folder.join("Pharo.image")).unwrap(), b"image");
+    }
+}
@@ -3,3 +3,77 @@ mod folder;
 
 pub use file::FileToMove;
 pub use folder::FolderToMove;
+
+use crate::Result;
+use filetime::FileTime;
+use std::fs::Metadata;
+use std::path::Path;
+
+/// Copies a file keeping its permissions, access and modification time.
+/// A symlink is recreated pointing to the same target instead of copying what it points to.
+/// Returns the amount of copied bytes
+pub fn copy_file_preserving_metadata(file: &Path, target: &Path) -> Result<u64> {
+    let metadata = std::fs::symlink_metadata(file)?;
+
+    if target.symlink_metadata().is_ok() {
+        std::fs::remove_file(target)?;
+    }
+
+    if metadata.file_type().is_symlink() {
+        copy_symlink(file, target)?;
+        return Ok(0);
+    }
+
+    // copying also copies the permissions
+    let size = std::fs::copy(file, target)?;
+    preserve_timestamps(&metadata, target)?;
+    Ok(size)
+}
+
+/// Renames a file or a symlink, otherwise, for example when the target is on a different filesystem,
+/// copies it preserving the metadata and removes the original. Returns the size of the moved file
+fn move_file_preserving_metadata(file: &Path, target: &Path) -> Result<u64> {
+    let size = std::fs::symlink_metadata(file)?.len();
+
+    if std::fs::rename(file, target).is_err() {
+        copy_and_remove_file(file, target)?;
+    }
+    Ok(size)
+}
+
+/// Moves a file or a symlink by copying it preserving the metadata and removing the original,
+/// which also works across filesystems. Returns the size of the moved file
+fn copy_and_remove_file(file: &Path, target: &Path) -> Result<u64> {
+    let size = std::fs::symlink_metadata(file)?.len();
+
+    copy_file_preserving_metadata(file, target)?;
+    std::fs::remove_file(file)?;
+    Ok(size)
+}
+
+/// Sets the permissions, access and modification time of a copied folder to the ones of the original
+fn preserve_folder_metadata(metadata: &Metadata, target: &Path) -> Result<()> {
+    std::fs::set_permissions(target, metadata.permissions())?;
+    preserve_timestamps(metadata, target)
+}
+
+fn preserve_timestamps(metadata: &Metadata, target: &Path) -> Result<()> {
+    filetime::set_file_times(
+        target,
+        FileTime::from_last_access_time(metadata),
+        FileTime::from_last_modification_time(metadata),
+    )?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(symlink: &Path, target: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(symlink)?, target)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(symlink: &Path, target: &Path) -> Result<()> {
+    std::fs::copy(symlink, target)?;
+    Ok(())
+}
@@ -4,8 +4,10 @@ use clap::Parser;
 
 use crate::LocalBuildOptions;
 use crate::{
-    BuildOptions, CopyOptions, DownloadOptions, ReleaseBuildOptions, ReleaseOptions,
-    ReleaserOptions, RenameOptions, SetupOptions, StartOptions, TentativeOptions, TestOptions,
+    BuildOptions, CleanUpOptions, CopyOptions, DownloadOptions, ListPackagesOptions,
+    PublishOptions, ReleaseBuildOptions, ReleaseOptions, ReleaserOptions, RenameOptions,
+    SetupOptions, SnapshotOptions, StartOptions, TentativeOptions, TestHistoryOptions, TestOptions,
+    VerifyPackageOptions, VerifySignatureOptions,
 };
 
 pub const DEFAULT_DIRECTORY: &str = "glamoroustoolkit";
@@ -24,6 +26,9 @@ pub struct AppOptions {
     verbose: bool,
     #[clap(long, default_value = DEFAULT_DIRECTORY, parse(from_os_str))]
     workspace: PathBuf,
+    /// Do not create a snapshot of the image before the commands that modify it
+    #[clap(long)]
+    no_snapshot: bool,
     /// Specify a path to a gtoolkit app binary which will should be used by the installer,
     /// The binary
     #[clap(long, parse(from_os_str))]
@@ -51,41 +56,63 @@ pub enum SubCommand {
     /// Does not copy temporary files or logs
     #[clap(display_order = 6)]
     CopyTo(CopyOptions),
-    /// Rename the image deleting the old one
+    /// Rename the image and its changes, without launching the App unless `--mode vm` is specified
     #[clap(display_order = 7)]
     RenameTo(RenameOptions),
     /// Starts an application interactively, waits for a duration of delay to let it load completely then saves and quits.
     #[clap(display_order = 8)]
     Start(StartOptions),
     /// Cleans up an image after loading Glamorous Toolkit. It cleans up ssh keys, removes iceberg repositories
-    /// and garbage collects objects
+    /// and garbage collects objects. Other profiles also reclaim the disk space used by the workspace
     #[clap(display_order = 9)]
-    CleanUp,
-    /// Tests Glamorous Toolkit and exports the results.
+    CleanUp(CleanUpOptions),
+    /// Creates, lists, restores and deletes snapshots of the image, changes and gtoolkit.yaml.
+    /// A snapshot is also created automatically before `rename-to`, `setup --target release` and `clean-up`
     #[clap(display_order = 10)]
+    Snapshot(SnapshotOptions),
+    /// Tests Glamorous Toolkit and exports the results.
+    #[clap(display_order = 11)]
     Test(TestOptions),
+    /// Queries the history of the test runs recorded by `test`.
+    #[clap(display_order = 12)]
+    TestHistory(TestHistoryOptions),
+    /// Lists packages of the image that contain examples or Pharo's TestCase, one per line.
+    #[clap(display_order = 13)]
+    ListPackages(ListPackagesOptions),
     /// Package the GlamorousToolkit image as a tentative release.
-    #[clap(display_order = 11)]
+    #[clap(display_order = 14)]
     PackageTentative(TentativeOptions),
     /// Given a packaged tentative image, download the GlamorousToolkit app for the version specified in the .version file
-    #[clap(display_order = 12)]
+    #[clap(display_order = 15)]
     UnpackageTentative(TentativeOptions),
-    /// Package the GlamorousToolkit image and App for a release. Prints the path to the created package in the `stdout`
-    #[clap(display_order = 13)]
+    /// Package the GlamorousToolkit image and App for a release. Prints the paths to the created packages in the `stdout`, one per line
+    #[clap(display_order = 16)]
     PackageRelease(ReleaseOptions),
+    /// Check that a release or tentative package contains the image, its resources and the App for the target.
+    /// Reports missing or unexpected entries and files that do not match the checksums of the package manifest,
+    /// and fails if there are any
+    #[clap(display_order = 17)]
+    VerifyPackage(VerifyPackageOptions),
+    /// Check a detached signature of a file created by `package-release` with a signing key
+    #[clap(display_order = 18)]
+    VerifySignature(VerifySignatureOptions),
+    /// Upload release packages with their manifests and checksums to a GitHub release or an S3-compatible bucket.
+    /// Files that are already uploaded with the same checksum are skipped
+    #[clap(display_order = 19)]
+    Publish(PublishOptions),
     /// Run the gtoolkit-releaser to release glamorous toolkit
-    #[clap(display_order = 14)]
+    #[clap(display_order = 20)]
     RunReleaser(ReleaserOptions),
     /// Display the Debug information of the AppOptions
-    #[clap(display_order = 15)]
+    #[clap(display_order = 21)]
     PrintDebug,
     /// Display the version of the glamorous toolkit image from the .yaml file in the workspace.
     /// Fails if the .yaml file wasn't found.
-    #[clap(display_order = 16)]
+    #[clap(display_order = 22)]
     PrintGtoolkitImageVersion,
     /// Display the version of the glamorous toolkit app from the .yaml file in the workspace.
     /// Fails if the .yaml file wasn't found.
-    #[clap(display_order = 17)]
+    #[clap(display_order = 23)]
     PrintGtoolkitAppVersion,
 }
 
@@ -106,4 +133,8 @@ impl AppOptions {
     pub fn verbose(&self) -> bool {
         self.verbose
     }
+
+    pub fn no_snapshot(&self) -> bool {
+        self.no_snapshot
+    }
 }
@@ -0,0 +1,186 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::{InstallerError, Result};
+
+/// Release notes are kept in the workspace between `setup --target release` and `package-release`
+pub const RELEASE_NOTES_FILE: &str = "release-notes.json";
+pub const RELEASE_NOTES_MARKDOWN_FILE: &str = "release-notes.md";
+
+/// A repository name under which commits without a repository are grouped
+const OTHER_REPOSITORY: &str = "Other";
+
+/// New commits since the previous release grouped by repository
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReleaseNotes {
+    pub version: String,
+    pub date: DateTime<Utc>,
+    pub repositories: Vec<RepositoryCommits>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RepositoryCommits {
+    pub repository: String,
+    pub commits: Vec<String>,
+}
+
+impl ReleaseNotes {
+    /// Groups the output of `printNewCommits` by repository.
+    /// A line without indentation starts a new repository, and all following indented
+    /// or bulleted lines are the commits of that repository
+    pub fn from_new_commits(version: impl ToString, new_commits: &str) -> Self {
+        let mut repositories: Vec<RepositoryCommits> = vec![];
+
+        for line in new_commits.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            let is_commit = line.starts_with(char::is_whitespace)
+                || trimmed.starts_with("- ")
+                || trimmed.starts_with("* ");
+
+            if !is_commit {
+                repositories.push(RepositoryCommits {
+                    repository: trimmed.trim_end_matches(':').to_string(),
+                    commits: vec![],
+                });
+                continue;
+            }
+
+            let commit = trimmed
+                .trim_start_matches("- ")
+                .trim_start_matches("* ")
+                .to_string();
+
+            if repositories.is_empty() {
+                repositories.push(RepositoryCommits {
+                    repository: OTHER_REPOSITORY.to_string(),
+                    commits: vec![],
+                });
+            }
+            repositories
+                .last_mut()
+                .expect("There is at least one repository")
+                .commits
+                .push(commit);
+        }
+
+        repositories.retain(|repository| !repository.commits.is_empty());
+
+        Self {
+            version: version.to_string(),
+            date: Utc::now(),
+            repositories,
+        }
+    }
+
+    pub fn read_from_file(file: impl AsRef<Path>) -> Result<Self> {
+        let file = file.as_ref();
+        let content = std::fs::read_to_string(file)?;
+        serde_json::from_str(&content)
+            .map_err(|error| InstallerError::ReleaseNotesError(file.to_path_buf(), error))
+    }
+
+    pub fn write_json(&self, file: impl AsRef<Path>) -> Result<()> {
+        let file = file.as_ref();
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|error| InstallerError::ReleaseNotesError(file.to_path_buf(), error))?;
+        std::fs::write(file, content)?;
+        Ok(())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# Glamorous Toolkit v{}\n", &self.version);
+
+        if self.repositories.is_empty() {
+            markdown.push_str("\nNo new commits.\n");
+        }
+
+        for repository in &self.repositories {
+            markdown.push_str(&format!("\n## {}\n\n", &repository.repository));
+            for commit in &repository.commits {
+                markdown.push_str(&format!("- {}\n", commit));
+            }
+        }
+        markdown
+    }
+
+    /// Writes both the Markdown and json release notes into a given directory
+    pub fn write_into(&self, directory: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(
+            directory.as_ref().join(RELEASE_NOTES_MARKDOWN_FILE),
+            self.to_markdown(),
+        )?;
+        self.write_json(directory.as_ref().join(RELEASE_NOTES_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The layout expected from `printNewCommits`: a repository per line followed by its indented or bulleted commits
+    const NEW_COMMITS: &str = "\
+* orphan commit without a repository
+
+feenkcom/gtoolkit:
+    - v1.0.42 [ci skip]
+    - fix the world menu
+feenkcom/Brick
+\t* add a scroll bar to the list
+feenkcom/lepiter:
+";
+
+    #[test]
+    fn groups_commits_by_repository() {
+        let notes = ReleaseNotes::from_new_commits("1.0.43", NEW_COMMITS);
+
+        let repositories = notes
+            .repositories
+            .iter()
+            .map(|repository| (repository.repository.as_str(), repository.commits.clone()))
+            .collect::<Vec<(&str, Vec<String>)>>();
+
+        assert_eq!(
+            repositories,
+            vec![
+                (
+                    OTHER_REPOSITORY,
+                    vec!["orphan commit without a repository".to_string()]
+                ),
+                (
+                    "feenkcom/gtoolkit",
+                    vec![
+                        "v1.0.42 [ci skip]".to_string(),
+                        "fix the world menu".to_string()
+                    ]
+                ),
+                (
+                    "feenkcom/Brick",
+                    vec!["add a scroll bar to the list".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_without_commits() {
+        let notes = ReleaseNotes::from_new_commits("1.0.43", "");
+        assert_eq!(
+            notes.to_markdown(),
+            "# Glamorous Toolkit v1.0.43\n\nNo new commits.\n"
+        );
+    }
+
+    #[test]
+    fn markdown_lists_commits_per_repository() {
+        let notes = ReleaseNotes::from_new_commits("1.0.43", "feenkcom/gtoolkit\n  fix\n");
+        assert_eq!(
+            notes.to_markdown(),
+            "# Glamorous Toolkit v1.0.43\n\n## feenkcom/gtoolkit\n\n- fix\n"
+        );
+    }
+}
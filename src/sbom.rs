@@ -0,0 +1,340 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ArgEnum;
+use serde_json::{json, Value};
+
+use crate::{Application, CustomerLevel, ImageSeed, InstallerError, PlatformOS, Result};
+
+const SUPPLIER_NAME: &str = "feenk gmbh";
+const SUPPLIER_URL: &str = "https://feenk.com";
+const GTOOLKIT_REPOSITORY_URL: &str = "https://github.com/feenkcom/gtoolkit";
+const RELEASER_REPOSITORY_URL: &str = "https://github.com/feenkcom/gtoolkit-releaser";
+const VM_REPOSITORY_URL: &str = "https://github.com/feenkcom/gtoolkit-vm";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+pub enum SbomFormat {
+    #[clap(name = "cyclonedx")]
+    CycloneDx,
+    Spdx,
+}
+
+impl SbomFormat {
+    /// The name of the software bill of materials within the package
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            SbomFormat::CycloneDx => "sbom.cdx.json",
+            SbomFormat::Spdx => "sbom.spdx.json",
+        }
+    }
+}
+
+/// A repository loaded into the image, as reported by Iceberg
+#[derive(Debug, Clone)]
+pub struct LoadedRepository {
+    pub name: String,
+    pub commit: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ComponentKind {
+    Application,
+    Library,
+    Data,
+}
+
+/// A component of the release, independent of the format of the software bill of materials
+#[derive(Debug, Clone)]
+struct Component {
+    id: String,
+    kind: ComponentKind,
+    name: String,
+    version: Option<String>,
+    url: Option<String>,
+    properties: Vec<(String, String)>,
+}
+
+/// A software bill of materials of a release package, that lists the image, the GlamorousToolkit App,
+/// the seed image, the versions of the loaders and optionally the repositories loaded into the image
+#[derive(Debug, Clone)]
+pub struct Sbom {
+    release: Component,
+    components: Vec<Component>,
+    timestamp: String,
+}
+
+impl Sbom {
+    pub fn new(
+        application: &Application,
+        target: PlatformOS,
+        customer_level: CustomerLevel,
+        repositories: &[LoadedRepository],
+    ) -> Self {
+        let release = Component {
+            id: "GlamorousToolkit".to_string(),
+            kind: ComponentKind::Application,
+            name: "GlamorousToolkit".to_string(),
+            version: Some(application.image_version().to_string()),
+            url: Some(GTOOLKIT_REPOSITORY_URL.to_string()),
+            properties: vec![
+                ("target".to_string(), target.as_str().to_string()),
+                (
+                    "image_name".to_string(),
+                    application.image_name().to_string(),
+                ),
+            ],
+        };
+
+        let mut components = vec![Component {
+            id: "GlamorousToolkit-App".to_string(),
+            kind: ComponentKind::Application,
+            name: "GlamorousToolkit App".to_string(),
+            version: Some(application.app_version().to_string()),
+            url: Some(VM_REPOSITORY_URL.to_string()),
+            properties: vec![
+                ("target".to_string(), target.as_str().to_string()),
+                (
+                    "customer_level".to_string(),
+                    customer_level.as_str().to_string(),
+                ),
+            ],
+        }];
+
+        components.push(Self::seed_component(application.image_seed()));
+
+        if let Some(loader_version_info) = application.loader_version_info() {
+            components.push(Component {
+                id: "gtoolkit-loader".to_string(),
+                kind: ComponentKind::Library,
+                name: "gtoolkit".to_string(),
+                version: Some(loader_version_info.gtoolkit_version().to_string()),
+                url: Some(GTOOLKIT_REPOSITORY_URL.to_string()),
+                properties: vec![],
+            });
+            components.push(Component {
+                id: "gtoolkit-releaser".to_string(),
+                kind: ComponentKind::Library,
+                name: "gtoolkit-releaser".to_string(),
+                version: Some(loader_version_info.releaser_version().to_string()),
+                url: Some(RELEASER_REPOSITORY_URL.to_string()),
+                properties: vec![],
+            });
+        }
+
+        // different names may be sanitized into the same id, the index keeps them unique
+        for (index, repository) in repositories.iter().enumerate() {
+            components.push(Component {
+                id: format!(
+                    "repository-{}-{}",
+                    Self::sanitize_id(&repository.name),
+                    index
+                ),
+                kind: ComponentKind::Library,
+                name: repository.name.clone(),
+                version: repository.commit.clone(),
+                url: repository.url.clone(),
+                properties: vec![],
+            });
+        }
+
+        Self {
+            release,
+            components,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+
+    /// Sets the creation time of the document, for example to make it reproducible
+    pub fn created_at(mut self, time: DateTime<Utc>) -> Self {
+        self.timestamp = time.to_rfc3339_opts(SecondsFormat::Secs, true);
+        self
+    }
+
+    fn seed_component(image_seed: &ImageSeed) -> Component {
+        let (name, url) = match image_seed {
+            ImageSeed::Url(url) => (
+                url.path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or("seed-image")
+                    .to_string(),
+                Some(url.to_string()),
+            ),
+            ImageSeed::Zip(path) | ImageSeed::Image(path) => (
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "seed-image".to_string()),
+                None,
+            ),
+        };
+
+        Component {
+            id: "seed-image".to_string(),
+            kind: ComponentKind::Data,
+            name,
+            version: None,
+            url,
+            properties: vec![],
+        }
+    }
+
+    /// SPDX identifiers and CycloneDX references may only contain letters, digits, `.` and `-`
+    fn sanitize_id(id: &str) -> String {
+        id.chars()
+            .map(|character| {
+                if character.is_ascii_alphanumeric() || character == '.' || character == '-' {
+                    character
+                } else {
+                    '-'
+                }
+            })
+            .collect()
+    }
+
+    pub fn to_json(&self, format: SbomFormat) -> Value {
+        match format {
+            SbomFormat::CycloneDx => self.to_cyclonedx(),
+            SbomFormat::Spdx => self.to_spdx(),
+        }
+    }
+
+    fn to_cyclonedx(&self) -> Value {
+        let component = |component: &Component| {
+            let mut value = json!({
+                "type": match component.kind {
+                    ComponentKind::Application => "application",
+                    ComponentKind::Library => "library",
+                    ComponentKind::Data => "data",
+                },
+                "bom-ref": &component.id,
+                "name": &component.name,
+            });
+            if let Some(ref version) = component.version {
+                value["version"] = json!(version);
+            }
+            if let Some(ref url) = component.url {
+                value["externalReferences"] = json!([{
+                    "type": if component.kind == ComponentKind::Data { "distribution" } else { "vcs" },
+                    "url": url,
+                }]);
+            }
+            if !component.properties.is_empty() {
+                value["properties"] = component
+                    .properties
+                    .iter()
+                    .map(|(name, value)| json!({ "name": format!("gtoolkit:{}", name), "value": value }))
+                    .collect();
+            }
+            value
+        };
+
+        let mut release = component(&self.release);
+        release["supplier"] = json!({ "name": SUPPLIER_NAME, "url": [SUPPLIER_URL] });
+
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "timestamp": &self.timestamp,
+                "tools": {
+                    "components": [{
+                        "type": "application",
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    }]
+                },
+                "component": release,
+            },
+            "components": self.components.iter().map(component).collect::<Vec<Value>>(),
+            "dependencies": [{
+                "ref": &self.release.id,
+                "dependsOn": self.components.iter().map(|each| each.id.clone()).collect::<Vec<String>>(),
+            }],
+        })
+    }
+
+    fn to_spdx(&self) -> Value {
+        let spdx_id = |component: &Component| format!("SPDXRef-{}", component.id);
+        let package = |component: &Component| {
+            let mut value = json!({
+                "SPDXID": spdx_id(component),
+                "name": &component.name,
+                "downloadLocation": component.url.as_deref().unwrap_or("NOASSERTION"),
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": "NOASSERTION",
+                "copyrightText": "NOASSERTION",
+                "primaryPackagePurpose": match component.kind {
+                    ComponentKind::Application => "APPLICATION",
+                    ComponentKind::Library => "LIBRARY",
+                    ComponentKind::Data => "FILE",
+                },
+            });
+            if let Some(ref version) = component.version {
+                value["versionInfo"] = json!(version);
+            }
+            if !component.properties.is_empty() {
+                value["comment"] = json!(component
+                    .properties
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect::<Vec<String>>()
+                    .join(", "));
+            }
+            value
+        };
+
+        let mut release = package(&self.release);
+        release["supplier"] = json!(format!("Organization: {}", SUPPLIER_NAME));
+
+        let mut packages = vec![release];
+        packages.extend(self.components.iter().map(package));
+
+        let mut relationships = vec![json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": spdx_id(&self.release),
+        })];
+        relationships.extend(self.components.iter().map(|component| {
+            json!({
+                "spdxElementId": spdx_id(&self.release),
+                "relationshipType": "CONTAINS",
+                "relatedSpdxElement": spdx_id(component),
+            })
+        }));
+
+        let name = format!(
+            "{}-v{}",
+            &self.release.name,
+            self.release.version.as_deref().unwrap_or_default()
+        );
+
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": &name,
+            "documentNamespace": format!("https://gtoolkit.com/spdx/{}-{}", name, &self.timestamp),
+            "creationInfo": {
+                "created": &self.timestamp,
+                "creators": [
+                    format!("Organization: {}", SUPPLIER_NAME),
+                    format!("Tool: {}-{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+                ],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+
+    /// Writes the software bill of materials in a given format into a directory and returns its path
+    pub fn write_into(&self, directory: impl AsRef<Path>, format: SbomFormat) -> Result<PathBuf> {
+        let file = directory.as_ref().join(format.file_name());
+        let content = serde_json::to_string_pretty(&self.to_json(format))
+            .map_err(|error| InstallerError::SbomSerializationError(file.clone(), error))?;
+        std::fs::write(&file, content)?;
+        Ok(file)
+    }
+}
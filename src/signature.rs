@@ -0,0 +1,166 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use minisign::{PublicKey, PublicKeyBox, SecretKey, SecretKeyBox, SignatureBox};
+
+use crate::{InstallerError, Result};
+
+/// A minisign secret key used to sign release packages
+pub const FEENK_SIGNING_KEY_ENV: &str = "FEENK_SIGNING_KEY";
+/// A password of an encrypted secret key
+pub const FEENK_SIGNING_KEY_PASSWORD_ENV: &str = "FEENK_SIGNING_KEY_PASSWORD";
+/// A minisign public key used to verify the signatures of the downloaded files
+pub const FEENK_PUBLIC_KEY_ENV: &str = "FEENK_PUBLIC_KEY";
+
+/// Returns the path of the detached signature of a given file: `<file>.sig`
+pub fn signature_path(file: impl AsRef<Path>) -> PathBuf {
+    let file = file.as_ref();
+    let mut file_name = file
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".sig");
+    file.with_file_name(file_name)
+}
+
+/// Signs files with an ed25519 key, writing minisign-compatible detached signatures
+pub struct Signer {
+    secret_key: SecretKey,
+}
+
+impl Signer {
+    /// Reads the secret key from a given file, otherwise from the `FEENK_SIGNING_KEY` environment variable.
+    /// Encrypted keys are decrypted with the password from `FEENK_SIGNING_KEY_PASSWORD`.
+    /// Returns `None` if no key is configured
+    pub fn from_file_or_env(key_file: Option<&Path>) -> Result<Option<Self>> {
+        let key = match key_file {
+            Some(key_file) => std::fs::read_to_string(key_file)?,
+            None => match std::env::var(FEENK_SIGNING_KEY_ENV) {
+                Ok(key) => key,
+                Err(_) => return Ok(None),
+            },
+        };
+
+        let key_box =
+            SecretKeyBox::from_string(key.trim()).map_err(InstallerError::SigningKeyError)?;
+        let secret_key = match std::env::var(FEENK_SIGNING_KEY_PASSWORD_ENV) {
+            Ok(password) => key_box.into_secret_key(Some(password)),
+            Err(_) => key_box.into_unencrypted_secret_key(),
+        }
+        .map_err(InstallerError::SigningKeyError)?;
+
+        Ok(Some(Self { secret_key }))
+    }
+
+    /// Signs a file and writes the signature next to it. Returns the path of the signature
+    pub fn sign(&self, file: impl AsRef<Path>) -> Result<PathBuf> {
+        let file = file.as_ref();
+        let trusted_comment = format!(
+            "file:{}",
+            file.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        );
+
+        let signature = minisign::sign(
+            None,
+            &self.secret_key,
+            BufReader::new(File::open(file)?),
+            Some(trusted_comment.as_str()),
+            Some("signature from gt-installer"),
+        )
+        .map_err(|error| InstallerError::SigningError(file.to_path_buf(), error))?;
+
+        let signature_file = signature_path(file);
+        std::fs::write(&signature_file, signature.into_string())?;
+        Ok(signature_file)
+    }
+}
+
+/// Verifies minisign-compatible detached signatures
+pub struct SignatureVerifier {
+    public_key: PublicKey,
+}
+
+impl SignatureVerifier {
+    /// Creates a verifier for a given public key, otherwise for the one in the `FEENK_PUBLIC_KEY` environment variable.
+    /// The key is either a path to a minisign .pub file, or the base64 encoded key.
+    /// Returns `None` if no key is configured
+    pub fn from_key_or_env(public_key: Option<&str>) -> Result<Option<Self>> {
+        let public_key = match public_key {
+            Some(public_key) => public_key.to_string(),
+            None => match std::env::var(FEENK_PUBLIC_KEY_ENV) {
+                Ok(public_key) => public_key,
+                Err(_) => return Ok(None),
+            },
+        };
+
+        let public_key = if Path::new(&public_key).is_file() {
+            PublicKeyBox::from_string(std::fs::read_to_string(&public_key)?.trim())
+                .and_then(|key_box| key_box.into_public_key())
+        } else {
+            PublicKey::from_base64(public_key.trim())
+        }
+        .map_err(InstallerError::PublicKeyError)?;
+
+        Ok(Some(Self { public_key }))
+    }
+
+    /// Verifies the signature of a file, fails if the signature is missing or does not match
+    pub fn verify(&self, file: impl AsRef<Path>, signature: impl AsRef<Path>) -> Result<()> {
+        let file = file.as_ref();
+        let signature = signature.as_ref();
+
+        if !signature.is_file() {
+            return InstallerError::SignatureNotFound(signature.to_path_buf()).into();
+        }
+
+        let signature_box = SignatureBox::from_string(&std::fs::read_to_string(signature)?)
+            .map_err(|error| {
+                InstallerError::SignatureVerificationFailed(file.to_path_buf(), error)
+            })?;
+
+        minisign::verify(
+            &self.public_key,
+            &signature_box,
+            BufReader::new(File::open(file)?),
+            true,
+            false,
+            false,
+        )
+        .map_err(|error| InstallerError::SignatureVerificationFailed(file.to_path_buf(), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minisign::KeyPair;
+
+    #[test]
+    fn signs_with_an_unencrypted_key_and_verifies() {
+        let directory = tempfile::tempdir().unwrap();
+        let key_pair = KeyPair::generate_unencrypted_keypair().unwrap();
+        let key_file = directory.path().join("release.key");
+        std::fs::write(&key_file, key_pair.sk.to_box(None).unwrap().into_string()).unwrap();
+
+        let package = directory.path().join("GlamorousToolkit.zip");
+        std::fs::write(&package, b"package").unwrap();
+
+        let signer = Signer::from_file_or_env(Some(&key_file)).unwrap().unwrap();
+        let signature = signer.sign(&package).unwrap();
+        assert_eq!(signature, directory.path().join("GlamorousToolkit.zip.sig"));
+
+        let verifier = SignatureVerifier::from_key_or_env(Some(&key_pair.pk.to_base64()))
+            .unwrap()
+            .unwrap();
+        verifier.verify(&package, &signature).unwrap();
+
+        std::fs::write(&package, b"tampered").unwrap();
+        assert!(matches!(
+            verifier.verify(&package, &signature),
+            Err(InstallerError::SignatureVerificationFailed(..))
+        ));
+    }
+}
@@ -0,0 +1,466 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::{copy_file_preserving_metadata, Application, InstallerError, Result};
+
+/// A folder within the workspace in which the snapshots are stored, one folder per snapshot
+pub const SNAPSHOTS_DIRECTORY: &str = "snapshots";
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const COMPRESSED_EXTENSION: &str = "zst";
+/// The files of a snapshot are restored into temporary files with this extension before replacing the originals
+const RESTORING_EXTENSION: &str = "restoring";
+/// Older automatic snapshots are deleted when a new one is created
+const MAX_AUTOMATIC_SNAPSHOTS: usize = 3;
+
+/// A copy of the image, changes and gtoolkit.yaml of the workspace at some moment in time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub image_version: String,
+    /// The command before which the snapshot was created automatically
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub automatic: Option<String>,
+    /// The files are compressed with zstd
+    pub compressed: bool,
+    /// Names of the files within the workspace stored in the snapshot
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshots {
+    directory: PathBuf,
+}
+
+impl Snapshots {
+    pub fn for_application(application: &Application) -> Self {
+        Self {
+            directory: application.workspace().join(SNAPSHOTS_DIRECTORY),
+        }
+    }
+
+    /// Returns all snapshots, the oldest first
+    pub fn list(&self) -> Result<Vec<Snapshot>> {
+        if !self.directory.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut snapshots = vec![];
+        for entry in std::fs::read_dir(&self.directory)? {
+            let snapshot_file = entry?.path().join(SNAPSHOT_FILE);
+            // a snapshot that failed to be created has no description
+            if snapshot_file.exists() {
+                snapshots.push(Self::read_snapshot(&snapshot_file)?);
+            }
+        }
+        snapshots.sort_by_key(|snapshot| snapshot.created_at);
+        Ok(snapshots)
+    }
+
+    pub fn find(&self, name: &str) -> Result<Snapshot> {
+        let snapshot_file = self.snapshot_directory(name)?.join(SNAPSHOT_FILE);
+        if !snapshot_file.exists() {
+            return InstallerError::SnapshotNotFound(name.to_string()).into();
+        }
+        Self::read_snapshot(&snapshot_file)
+    }
+
+    /// Copies the image, changes and gtoolkit.yaml of the workspace into a new snapshot.
+    /// The name defaults to the current date and time
+    pub fn create(
+        &self,
+        application: &Application,
+        name: Option<&str>,
+        compress: bool,
+    ) -> Result<Snapshot> {
+        let created_at = Utc::now();
+        let name = name
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| created_at.format("%Y-%m-%d-%H%M%S").to_string());
+        self.create_named(application, name, created_at, compress, None)
+    }
+
+    /// Creates an uncompressed snapshot before running a given command that modifies the image,
+    /// keeping only the most recent automatic snapshots
+    pub fn create_automatic(&self, application: &Application, command: &str) -> Result<Snapshot> {
+        let created_at = Utc::now();
+        let name = self.unique_name(&format!(
+            "{}-{}",
+            command,
+            created_at.format("%Y-%m-%d-%H%M%S")
+        ));
+        let snapshot = self.create_named(
+            application,
+            name,
+            created_at,
+            false,
+            Some(command.to_string()),
+        )?;
+
+        let automatic_snapshots = self
+            .list()?
+            .into_iter()
+            .filter(|snapshot| snapshot.automatic.is_some())
+            .collect::<Vec<Snapshot>>();
+        let outdated = automatic_snapshots
+            .len()
+            .saturating_sub(MAX_AUTOMATIC_SNAPSHOTS);
+        for snapshot in &automatic_snapshots[..outdated] {
+            self.delete(&snapshot.name)?;
+        }
+
+        Ok(snapshot)
+    }
+
+    fn create_named(
+        &self,
+        application: &Application,
+        name: String,
+        created_at: DateTime<Utc>,
+        compress: bool,
+        automatic: Option<String>,
+    ) -> Result<Snapshot> {
+        let directory = self.snapshot_directory(&name)?;
+        if directory.exists() {
+            return InstallerError::SnapshotAlreadyExists(name).into();
+        }
+        std::fs::create_dir_all(&directory)?;
+
+        let image = application.image();
+        let files = [
+            image.clone(),
+            image.with_extension("changes"),
+            application.serialization_file(),
+        ]
+        .into_iter()
+        .filter(|file| file.exists())
+        .collect::<Vec<PathBuf>>();
+
+        let snapshot = Snapshot {
+            name,
+            created_at,
+            image_version: application.image_version().to_string(),
+            automatic,
+            compressed: compress,
+            files: files
+                .iter()
+                .filter_map(|file| file.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .collect(),
+        };
+
+        let stored = files
+            .iter()
+            .zip(&snapshot.files)
+            .try_for_each(|(file, name)| {
+                Self::store_file(
+                    file,
+                    &Self::stored_file(&directory, name, compress),
+                    compress,
+                )
+            })
+            .and_then(|_| {
+                let content = serde_json::to_string_pretty(&snapshot).map_err(|error| {
+                    InstallerError::SnapshotSerializationError(directory.clone(), error)
+                })?;
+                std::fs::write(directory.join(SNAPSHOT_FILE), content)?;
+                Ok(())
+            });
+
+        if let Err(error) = stored {
+            std::fs::remove_dir_all(&directory)?;
+            return Err(error);
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Replaces the image, changes and gtoolkit.yaml of the workspace with the ones from a snapshot.
+    /// All files are restored next to the originals first, so that a failure leaves the workspace untouched.
+    /// The current image and changes are removed if they are named differently than the restored ones
+    pub fn restore(&self, application: &Application, name: &str) -> Result<Snapshot> {
+        let snapshot = self.find(name)?;
+        let directory = self.snapshot_directory(name)?;
+
+        let mut restored_files = vec![];
+        for file in &snapshot.files {
+            let stored_file = Self::stored_file(&directory, file, snapshot.compressed);
+            let restored_file = application
+                .workspace()
+                .join(format!("{}.{}", file, RESTORING_EXTENSION));
+            restored_files.push((restored_file.clone(), application.workspace().join(file)));
+
+            if let Err(error) =
+                Self::restore_file(&stored_file, &restored_file, snapshot.compressed)
+            {
+                for (restored_file, _) in &restored_files {
+                    std::fs::remove_file(restored_file).ok();
+                }
+                return Err(error);
+            }
+        }
+
+        for (restored_file, target) in &restored_files {
+            std::fs::rename(restored_file, target)?;
+        }
+
+        let image = application.image();
+        for file in [image.clone(), image.with_extension("changes")] {
+            let is_restored = restored_files.iter().any(|(_, target)| target == &file);
+            if !is_restored && file.exists() {
+                std::fs::remove_file(&file)?;
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.find(name)?;
+        std::fs::remove_dir_all(self.snapshot_directory(name)?)?;
+        Ok(())
+    }
+
+    /// Returns the disk space used by a snapshot
+    pub fn size_of(&self, snapshot: &Snapshot) -> Result<u64> {
+        let directory = self.snapshot_directory(&snapshot.name)?;
+        let mut size = 0;
+        for file in &snapshot.files {
+            size +=
+                std::fs::metadata(Self::stored_file(&directory, file, snapshot.compressed))?.len();
+        }
+        Ok(size)
+    }
+
+    /// Appends a number to the name if a snapshot with such name already exists
+    fn unique_name(&self, name: &str) -> String {
+        let mut unique_name = name.to_string();
+        let mut index = 1;
+        while self.directory.join(&unique_name).exists() {
+            unique_name = format!("{}-{}", name, index);
+            index += 1;
+        }
+        unique_name
+    }
+
+    fn snapshot_directory(&self, name: &str) -> Result<PathBuf> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return InstallerError::InvalidSnapshotName(name.to_string()).into();
+        }
+        Ok(self.directory.join(name))
+    }
+
+    fn stored_file(directory: &Path, name: &str, compressed: bool) -> PathBuf {
+        if compressed {
+            directory.join(format!("{}.{}", name, COMPRESSED_EXTENSION))
+        } else {
+            directory.join(name)
+        }
+    }
+
+    fn store_file(file: &Path, stored_file: &Path, compress: bool) -> Result<()> {
+        if compress {
+            zstd::stream::copy_encode(File::open(file)?, File::create(stored_file)?, 0)?;
+        } else {
+            copy_file_preserving_metadata(file, stored_file)?;
+        }
+        Ok(())
+    }
+
+    fn restore_file(stored_file: &Path, restored_file: &Path, compressed: bool) -> Result<()> {
+        if compressed {
+            zstd::stream::copy_decode(File::open(stored_file)?, File::create(restored_file)?)?;
+        } else {
+            copy_file_preserving_metadata(stored_file, restored_file)?;
+        }
+        Ok(())
+    }
+
+    fn read_snapshot(snapshot_file: &Path) -> Result<Snapshot> {
+        let content = std::fs::read_to_string(snapshot_file)?;
+        serde_json::from_str(&content).map_err(|error| {
+            InstallerError::SnapshotSerializationError(snapshot_file.to_path_buf(), error)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageSeed;
+
+    fn workspace() -> (tempfile::TempDir, Application) {
+        let directory = tempfile::tempdir().unwrap();
+        let application = Application::for_tests(directory.path());
+        std::fs::write(application.image(), b"image").unwrap();
+        std::fs::write(application.image().with_extension("changes"), b"changes").unwrap();
+        application.serialize_into_file().unwrap();
+        (directory, application)
+    }
+
+    fn names(snapshots: &Snapshots) -> Vec<String> {
+        snapshots
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|snapshot| snapshot.name)
+            .collect()
+    }
+
+    fn round_trip(compress: bool) {
+        let (_directory, application) = workspace();
+        let snapshots = Snapshots::for_application(&application);
+
+        let snapshot = snapshots
+            .create(&application, Some("before"), compress)
+            .unwrap();
+        assert_eq!(
+            snapshot.files,
+            vec![
+                "GlamorousToolkit.image",
+                "GlamorousToolkit.changes",
+                "gtoolkit.yaml"
+            ]
+        );
+        assert_eq!(names(&snapshots), vec!["before"]);
+        assert!(snapshots.size_of(&snapshot).unwrap() > 0);
+
+        std::fs::write(application.image(), b"modified image").unwrap();
+        std::fs::remove_file(application.image().with_extension("changes")).unwrap();
+
+        snapshots.restore(&application, "before").unwrap();
+        assert_eq!(std::fs::read(application.image()).unwrap(), b"image");
+        assert_eq!(
+            std::fs::read(application.image().with_extension("changes")).unwrap(),
+            b"changes"
+        );
+        assert!(!application
+            .workspace()
+            .join(format!("GlamorousToolkit.image.{}", RESTORING_EXTENSION))
+            .exists());
+
+        snapshots.delete("before").unwrap();
+        assert!(names(&snapshots).is_empty());
+        assert!(matches!(
+            snapshots.find("before"),
+            Err(InstallerError::SnapshotNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn create_list_restore_delete() {
+        round_trip(false);
+    }
+
+    #[test]
+    fn create_list_restore_delete_compressed() {
+        round_trip(true);
+    }
+
+    #[test]
+    fn failed_restore_keeps_the_image() {
+        let (_directory, application) = workspace();
+        let snapshots = Snapshots::for_application(&application);
+        snapshots
+            .create(&application, Some("broken"), true)
+            .unwrap();
+
+        let stored_changes = Snapshots::stored_file(
+            &snapshots.snapshot_directory("broken").unwrap(),
+            "GlamorousToolkit.changes",
+            true,
+        );
+        std::fs::write(stored_changes, b"not zstd").unwrap();
+        std::fs::write(application.image(), b"current image").unwrap();
+
+        assert!(snapshots.restore(&application, "broken").is_err());
+        assert_eq!(
+            std::fs::read(application.image()).unwrap(),
+            b"current image"
+        );
+        assert_eq!(
+            std::fs::read(application.image().with_extension("changes")).unwrap(),
+            b"changes"
+        );
+        let mut entries = std::fs::read_dir(application.workspace())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                "GlamorousToolkit.changes",
+                "GlamorousToolkit.image",
+                "gtoolkit.yaml",
+                SNAPSHOTS_DIRECTORY
+            ]
+        );
+    }
+
+    #[test]
+    fn restore_removes_a_differently_named_image() {
+        let (_directory, application) = workspace();
+        let snapshots = Snapshots::for_application(&application);
+        snapshots
+            .create(&application, Some("original"), false)
+            .unwrap();
+
+        let renamed_image = application.workspace().join("Renamed.image");
+        std::fs::rename(application.image(), &renamed_image).unwrap();
+        std::fs::rename(
+            application.image().with_extension("changes"),
+            renamed_image.with_extension("changes"),
+        )
+        .unwrap();
+        let mut renamed = application.clone();
+        renamed
+            .set_image_seed(ImageSeed::Image(renamed_image))
+            .unwrap();
+
+        snapshots.restore(&renamed, "original").unwrap();
+        assert!(application.image().exists());
+        assert!(application.image().with_extension("changes").exists());
+        assert!(!renamed.image().exists());
+        assert!(!renamed.image().with_extension("changes").exists());
+    }
+
+    #[test]
+    fn unique_name_appends_a_number() {
+        let (_directory, application) = workspace();
+        let snapshots = Snapshots::for_application(&application);
+        assert_eq!(snapshots.unique_name("test"), "test");
+
+        snapshots.create(&application, Some("test"), false).unwrap();
+        assert_eq!(snapshots.unique_name("test"), "test-1");
+
+        snapshots
+            .create(&application, Some("test-1"), false)
+            .unwrap();
+        assert_eq!(snapshots.unique_name("test"), "test-2");
+    }
+
+    #[test]
+    fn prunes_automatic_snapshots() {
+        let (_directory, application) = workspace();
+        let snapshots = Snapshots::for_application(&application);
+        snapshots
+            .create(&application, Some("manual"), false)
+            .unwrap();
+
+        let automatic = (0..MAX_AUTOMATIC_SNAPSHOTS + 2)
+            .map(|_| {
+                snapshots
+                    .create_automatic(&application, "test")
+                    .unwrap()
+                    .name
+            })
+            .collect::<Vec<String>>();
+
+        let mut expected = vec!["manual".to_string()];
+        expected.extend_from_slice(&automatic[2..]);
+        assert_eq!(names(&snapshots), expected);
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+
+use crate::{Application, CustomerLevel, InstallerError, PlatformOS, Result};
+
+/// Variables that can be used in the mustache templates of the package paths
+#[derive(Serialize, Debug, Clone)]
+pub struct TemplateVariables {
+    version: String,
+    app_version: String,
+    os: String,
+    arch: String,
+    target: String,
+    date: String,
+    customer_level: String,
+    image_name: String,
+    build_id: String,
+}
+
+/// A path of the package with mustache variables, for example "GlamorousToolkit-{{os}}-{{arch}}-v{{version}}.zip"
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+    path: PathBuf,
+}
+
+impl TemplateVariables {
+    pub fn new(
+        application: &Application,
+        target: PlatformOS,
+        customer_level: CustomerLevel,
+    ) -> Self {
+        let os = match target {
+            PlatformOS::MacOSX8664 | PlatformOS::MacOSAarch64 => "MacOS",
+            PlatformOS::WindowsX8664 | PlatformOS::WindowsAarch64 => "Windows",
+            PlatformOS::LinuxX8664 | PlatformOS::LinuxAarch64 => "Linux",
+            PlatformOS::AndroidAarch64 => "Android",
+        };
+
+        let arch = match target {
+            PlatformOS::MacOSX8664 => "x86_64",
+            PlatformOS::MacOSAarch64 => "aarch64",
+            PlatformOS::WindowsX8664 => "x86_64",
+            PlatformOS::WindowsAarch64 => "aarch64",
+            PlatformOS::LinuxX8664 => "x86_64",
+            PlatformOS::LinuxAarch64 => "aarch64",
+            PlatformOS::AndroidAarch64 => "aarch64",
+        };
+
+        Self {
+            version: application.image_version().to_string(),
+            app_version: application.app_version().to_string(),
+            os: os.to_string(),
+            arch: arch.to_string(),
+            target: target.as_str().to_string(),
+            date: Utc::now().format("%Y-%m-%d").to_string(),
+            customer_level: customer_level.as_str().to_string(),
+            image_name: application.image_name().to_string(),
+            build_id: Self::build_id(application),
+        }
+    }
+
+    /// Returns a git-describe-like identifier of the build in a form of `vX.Y.Z[-BUILD_NUMBER][-gCOMMIT]`,
+    /// where the build number and the commit are read from the `BUILD_NUMBER` and `GIT_COMMIT`
+    /// environment variables of the CI server when they are set
+    fn build_id(application: &Application) -> String {
+        let mut build_id = format!("v{}", application.image_version());
+        if let Ok(build_number) = std::env::var("BUILD_NUMBER") {
+            build_id.push_str(&format!("-{}", build_number));
+        }
+        if let Ok(commit) = std::env::var("GIT_COMMIT") {
+            build_id.push_str(&format!("-g{}", commit.chars().take(7).collect::<String>()));
+        }
+        build_id
+    }
+}
+
+impl PathTemplate {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Renders every component of the path with the given variables
+    pub fn render(&self, variables: &TemplateVariables) -> Result<PathBuf> {
+        self.path
+            .iter()
+            .map(|each| {
+                mustache::compile_str(each.to_string_lossy().as_ref())
+                    .and_then(|template| template.render_to_string(variables))
+                    .map(PathBuf::from)
+                    .map_err(|error| InstallerError::InvalidPathTemplate(self.path.clone(), error))
+            })
+            .collect()
+    }
+}
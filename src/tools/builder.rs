@@ -283,12 +283,22 @@ impl ToString for BuilderAppVersion {
 
 pub struct Builder;
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LoaderVersionInfo {
     gtoolkit_version: String,
     releaser_version: String,
 }
 
+impl LoaderVersionInfo {
+    pub fn gtoolkit_version(&self) -> &str {
+        self.gtoolkit_version.as_str()
+    }
+
+    pub fn releaser_version(&self) -> &str {
+        self.releaser_version.as_str()
+    }
+}
+
 impl Builder {
     pub fn new() -> Self {
         Self {}
@@ -360,6 +370,15 @@ impl Builder {
             application.set_app_version(version)
         }
 
+        // remember the chosen customer level to download the same App when unpackaging or releasing
+        if application.has_explicit_app_cli_binary() {
+            application.set_customer_level(build_options.customer_level);
+        } else {
+            application.set_customer_level(Downloader::resolve_customer_level(
+                build_options.customer_level,
+            )?);
+        }
+
         Checker::new()
             .check(application, build_options.should_overwrite())
             .await?;
@@ -390,6 +409,17 @@ impl Builder {
 
         files_to_download.download().await?;
 
+        // the Pro VM is verified when it is downloaded above
+        if !Downloader::should_download_pro_vm(build_options.customer_level)? {
+            Downloader::new()
+                .verify_glamorous_toolkit_vm_archive(
+                    application,
+                    application.host_platform(),
+                    build_options.customer_level,
+                )
+                .await?;
+        }
+
         println!("{}Extracting files...", EXTRACTING);
 
         let files_to_unzip = FilesToUnzip::new()
@@ -447,6 +477,10 @@ impl Builder {
         let loader_script_file_name =
             format!("load-gt-{}.st", &loader_version_info.gtoolkit_version);
 
+        // remember the loaded versions to list them in the software bill of materials of a release
+        application.set_loader_version_info(loader_version_info.clone());
+        application.serialize_into_file()?;
+
         println!("{}Creating build scripts...", CREATING);
         FileToCreate::new(
             application.workspace().join("load-patches.st"),
@@ -1,5 +1,84 @@
+use clap::{ArgEnum, Parser};
+use indicatif::HumanBytes;
+use parse_duration::parse as duration_parse;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+use wildmatch::WildMatch;
+
 use crate::gtoolkit::GToolkit;
-use crate::{Application, Result};
+use crate::{Application, Result, SPARKLE};
+
+const DEFAULT_LOGS_AGE: &str = "7 days";
+
+/// Files and folders of the workspace that are only needed while building the image
+const BUILD_LEFTOVERS: [&str; 5] = [
+    "seed-image",
+    "seed-image.zip",
+    "GlamorousToolkitApp*-v*.zip",
+    "GlamorousToolkitApp*-v*.zip.sig",
+    "load-*.st",
+];
+/// Folders within pharo-local that are re-created by the image when needed
+const PHARO_LOCAL_CACHES: [&str; 2] = ["ombu-sessions", "package-cache"];
+
+#[derive(Parser, Debug, Clone)]
+pub struct CleanUpOptions {
+    /// What to clean up: 'credentials' removes ssh keys and iceberg repositories from the image and garbage collects objects,
+    /// 'disk' removes the seed image, downloaded archives, generated loading scripts, old logs, Epicea sessions and caches
+    /// from the workspace, 'release' does both and removes all logs. Can be specified multiple times, defaults to 'credentials'
+    #[clap(
+        long = "profile",
+        arg_enum,
+        ignore_case = true,
+        multiple_occurrences = true
+    )]
+    pub profiles: Vec<CleanUpProfile>,
+    /// Logs modified within this amount of time are kept by the `disk` profile
+    #[clap(long, parse(try_from_str = duration_parse), default_value = DEFAULT_LOGS_AGE)]
+    pub logs_older_than: Duration,
+    /// Print what was removed and how much disk space was reclaimed
+    #[clap(long)]
+    pub report: bool,
+}
+
+impl CleanUpOptions {
+    /// Returns true if the clean-up modifies the image, not only the workspace
+    pub fn modifies_image(&self) -> bool {
+        self.profiles.is_empty()
+            || self.profiles.iter().any(|profile| {
+                matches!(
+                    profile,
+                    CleanUpProfile::Credentials | CleanUpProfile::Release
+                )
+            })
+    }
+
+    /// Returns how long the logs are kept when cleaning up the workspace,
+    /// or None if the profiles do not clean up the workspace
+    pub fn logs_kept_for(&self) -> Option<Duration> {
+        if self.profiles.contains(&CleanUpProfile::Release) {
+            Some(Duration::ZERO)
+        } else if self.profiles.contains(&CleanUpProfile::Disk) {
+            Some(self.logs_older_than)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+pub enum CleanUpProfile {
+    Credentials,
+    Disk,
+    Release,
+}
+
+/// A file or folder removed from the workspace, or the image that shrank after cleaning it up
+struct Reclaimed {
+    path: PathBuf,
+    size: u64,
+}
 
 pub struct Cleaner;
 
@@ -8,9 +87,265 @@ impl Cleaner {
         Self {}
     }
 
-    pub async fn clean(&self, application: &Application) -> Result<()> {
-        application.gtoolkit().perform_iceberg_clean_up()?;
+    pub async fn clean(
+        &self,
+        application: &Application,
+        clean_up_options: &CleanUpOptions,
+    ) -> Result<()> {
+        let mut reclaimed = vec![];
+
+        if clean_up_options.modifies_image() {
+            let size_before = Self::size_of(&application.image())?;
+            application.gtoolkit().perform_iceberg_clean_up()?;
+            let size_after = Self::size_of(&application.image())?;
+
+            reclaimed.push(Reclaimed {
+                path: application.image(),
+                size: size_before.saturating_sub(size_after),
+            });
+        }
+
+        if let Some(logs_older_than) = clean_up_options.logs_kept_for() {
+            reclaimed.extend(Self::clean_disk(application, logs_older_than)?);
+        }
+
+        if clean_up_options.report {
+            Self::print_report(application, &reclaimed);
+        }
 
         Ok(())
     }
+
+    /// Removes the leftovers of the build, caches and the logs older than a given amount of time
+    fn clean_disk(application: &Application, logs_older_than: Duration) -> Result<Vec<Reclaimed>> {
+        let workspace = application.workspace();
+        let image = application.image();
+        let logs_modified_before = SystemTime::now()
+            .checked_sub(logs_older_than)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let build_leftovers = BUILD_LEFTOVERS
+            .iter()
+            .map(|pattern| WildMatch::new(pattern))
+            .collect::<Vec<WildMatch>>();
+        let logs = WildMatch::new("*.log");
+
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(workspace)? {
+            let path = entry?.path();
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            // an image that was built or renamed within the seed image folder is still in use
+            if image.starts_with(&path) {
+                continue;
+            }
+
+            if build_leftovers.iter().any(|pattern| pattern.matches(&name))
+                || (logs.matches(&name)
+                    && std::fs::metadata(&path)?.modified()? <= logs_modified_before)
+            {
+                entries.push(path);
+            }
+        }
+
+        for cache in PHARO_LOCAL_CACHES {
+            let path = workspace.join("pharo-local").join(cache);
+            if path.exists() {
+                entries.push(path);
+            }
+        }
+
+        let mut reclaimed = vec![];
+        for path in entries {
+            let size = Self::size_of(&path)?;
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+            reclaimed.push(Reclaimed { path, size });
+        }
+        Ok(reclaimed)
+    }
+
+    /// Returns the size of a file or of all files within a folder. Symlinks are not followed
+    fn size_of(path: &Path) -> Result<u64> {
+        let mut size = 0;
+        for each in WalkDir::new(path).follow_links(false) {
+            let each = each.map_err(std::io::Error::from)?;
+            if each.file_type().is_file() {
+                size += each.metadata().map_err(std::io::Error::from)?.len();
+            }
+        }
+        Ok(size)
+    }
+
+    fn print_report(application: &Application, reclaimed: &[Reclaimed]) {
+        for each in reclaimed {
+            let path = each
+                .path
+                .strip_prefix(application.workspace())
+                .unwrap_or(&each.path);
+            println!(
+                "{:>12}  {}",
+                HumanBytes(each.size).to_string(),
+                path.display()
+            );
+        }
+
+        println!(
+            "{}Reclaimed {}",
+            SPARKLE,
+            HumanBytes(reclaimed.iter().map(|each| each.size).sum()).to_string()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    /// Creates a workspace with an image, the leftovers of its build, caches and logs.
+    /// The content of every file is as long as its name, so the files have different sizes
+    fn create_workspace(workspace: &Path) -> Application {
+        for file in [
+            "GlamorousToolkit.image",
+            "GlamorousToolkit.changes",
+            "gt-extra/feenk.png",
+            "seed-image/Pharo.image",
+            "seed-image/Pharo.changes",
+            "seed-image.zip",
+            "GlamorousToolkitApp-x86_64-unknown-linux-gnu-v1.0.0.zip",
+            "GlamorousToolkitApp-x86_64-unknown-linux-gnu-v1.0.0.zip.sig",
+            "load-gtoolkit.st",
+            "PharoDebug.log",
+            "gt-build.log",
+            "pharo-local/ombu-sessions/session.ombu",
+            "pharo-local/package-cache/gtoolkit.mcz",
+            "pharo-local/iceberg/README.md",
+        ] {
+            let path = workspace.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, file).unwrap();
+        }
+
+        let old = SystemTime::now() - Duration::from_secs(30 * DAY);
+        filetime::set_file_mtime(
+            workspace.join("gt-build.log"),
+            filetime::FileTime::from_system_time(old),
+        )
+        .unwrap();
+
+        Application::for_tests(workspace)
+    }
+
+    fn options(profiles: &[&str]) -> CleanUpOptions {
+        let mut args = vec!["clean-up"];
+        for profile in profiles {
+            args.extend(["--profile", profile]);
+        }
+        CleanUpOptions::parse_from(args)
+    }
+
+    /// Cleans up the workspace as the given profiles would,
+    /// returning the removed entries relative to the workspace and the reclaimed bytes
+    fn clean_disk(profiles: &[&str]) -> Option<(Vec<String>, u64)> {
+        let directory = tempfile::tempdir().unwrap();
+        let application = create_workspace(directory.path());
+
+        let logs_kept_for = options(profiles).logs_kept_for()?;
+        let reclaimed = Cleaner::clean_disk(&application, logs_kept_for).unwrap();
+        for each in &reclaimed {
+            assert!(!each.path.exists());
+        }
+
+        let mut removed = reclaimed
+            .iter()
+            .map(|each| {
+                each.path
+                    .strip_prefix(directory.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect::<Vec<String>>();
+        removed.sort();
+
+        assert!(directory.path().join("GlamorousToolkit.image").exists());
+        assert!(directory.path().join("gt-extra/feenk.png").exists());
+        assert!(directory
+            .path()
+            .join("pharo-local/iceberg/README.md")
+            .exists());
+
+        Some((removed, reclaimed.iter().map(|each| each.size).sum()))
+    }
+
+    /// The length of the names of the files, which is also their size
+    fn size_of(files: &[&str]) -> u64 {
+        files.iter().map(|file| file.len() as u64).sum()
+    }
+
+    #[test]
+    fn credentials_profile_does_not_clean_the_workspace() {
+        assert!(options(&[]).modifies_image());
+        assert!(clean_disk(&[]).is_none());
+        assert!(options(&["credentials"]).modifies_image());
+        assert!(clean_disk(&["credentials"]).is_none());
+    }
+
+    #[test]
+    fn disk_profile_removes_leftovers_caches_and_old_logs() {
+        assert!(!options(&["disk"]).modifies_image());
+
+        let (removed, reclaimed) = clean_disk(&["disk"]).unwrap();
+        assert_eq!(
+            removed,
+            vec![
+                "GlamorousToolkitApp-x86_64-unknown-linux-gnu-v1.0.0.zip",
+                "GlamorousToolkitApp-x86_64-unknown-linux-gnu-v1.0.0.zip.sig",
+                "gt-build.log",
+                "load-gtoolkit.st",
+                "pharo-local/ombu-sessions",
+                "pharo-local/package-cache",
+                "seed-image",
+                "seed-image.zip",
+            ]
+        );
+        assert_eq!(
+            reclaimed,
+            size_of(&[
+                "GlamorousToolkitApp-x86_64-unknown-linux-gnu-v1.0.0.zip",
+                "GlamorousToolkitApp-x86_64-unknown-linux-gnu-v1.0.0.zip.sig",
+                "gt-build.log",
+                "load-gtoolkit.st",
+                "pharo-local/ombu-sessions/session.ombu",
+                "pharo-local/package-cache/gtoolkit.mcz",
+                "seed-image/Pharo.image",
+                "seed-image/Pharo.changes",
+                "seed-image.zip",
+            ])
+        );
+    }
+
+    #[test]
+    fn release_profile_also_removes_recent_logs() {
+        let release = options(&["release"]);
+        assert!(release.modifies_image());
+        assert_eq!(release.logs_kept_for(), Some(Duration::ZERO));
+
+        let (removed, reclaimed) = clean_disk(&["release"]).unwrap();
+        assert!(removed.contains(&"PharoDebug.log".to_string()));
+        assert!(removed.contains(&"gt-build.log".to_string()));
+        assert_eq!(removed.len(), 9);
+        assert_eq!(
+            reclaimed,
+            clean_disk(&["disk"]).unwrap().1 + size_of(&["PharoDebug.log"])
+        );
+    }
 }
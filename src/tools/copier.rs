@@ -1,8 +1,18 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{ArgEnum, Parser};
+use file_matcher::{FileNamed, OneEntryNamed};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use wildmatch::WildMatch;
 
-use crate::{Application, Result};
-use file_matcher::{FileNamed, FolderNamed, OneEntry, OneEntryCopier, OneEntryNamed};
+use crate::{
+    copy_file_preserving_metadata, Application, InstallerError, Result, SERIALIZATION_FILE,
+    SNAPSHOTS_DIRECTORY,
+};
+
+/// Workspace entries that the image never modifies, so they can be linked instead of copied
+const IMMUTABLE_ENTRIES: [&str; 2] = ["*.sources", "gt-extra"];
+/// Logs, crash dumps and snapshots are not copied even by the full preset
+const FULL_PRESET_EXCLUDED_ENTRIES: [&str; 3] = ["*.log", "*.dmp", SNAPSHOTS_DIRECTORY];
 
 #[derive(Parser, Debug, Clone)]
 pub struct CopyOptions {
@@ -12,6 +22,64 @@ pub struct CopyOptions {
     /// Include an app binary when copying. When set to false, will only copy an image.
     #[clap(long, default_value = "true")]
     pub include_app: bool,
+    /// How to bring over the files that never change: the app binary, the sources and gt-extra.
+    /// The image and changes are always copied. Files that can not be linked, for example
+    /// because the destination is on another filesystem, are copied instead
+    #[clap(long, default_value = "copy", arg_enum, ignore_case = true)]
+    pub link: LinkMode,
+    /// Which entries of the workspace to copy: 'minimal' copies the image, changes, sources and gtoolkit.yaml,
+    /// 'default' also copies gt-extra and 'full' copies everything except logs, crash dumps and snapshots
+    #[clap(long, default_value = "default", arg_enum, ignore_case = true)]
+    pub preset: CopyPreset,
+    /// Also copy the workspace entries matching a glob pattern, for example `pharo-local`. Can be specified multiple times
+    #[clap(long, multiple_occurrences = true)]
+    pub include: Vec<String>,
+    /// Do not copy files or folders matching a glob pattern relative to the workspace,
+    /// for example `pharo-local/iceberg/*`. Can be specified multiple times
+    #[clap(long, multiple_occurrences = true)]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+pub enum LinkMode {
+    /// Hard-link the files, the copy shares them with the original workspace
+    Hard,
+    /// Clone the files on filesystems with copy-on-write support such as APFS, Btrfs or XFS
+    Reflink,
+    /// Copy the files
+    Copy,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+pub enum CopyPreset {
+    Minimal,
+    Default,
+    Full,
+}
+
+impl CopyPreset {
+    /// Glob patterns of the workspace entries to copy
+    fn included_entries(&self) -> Vec<&'static str> {
+        match self {
+            Self::Minimal => vec!["*.image", "*.changes", "*.sources", SERIALIZATION_FILE],
+            Self::Default => vec![
+                "*.image",
+                "*.changes",
+                "*.sources",
+                SERIALIZATION_FILE,
+                "gt-extra",
+            ],
+            Self::Full => vec!["*"],
+        }
+    }
+
+    /// Glob patterns of the files and folders not to copy
+    fn excluded_entries(&self) -> Vec<&'static str> {
+        match self {
+            Self::Minimal | Self::Default => vec![],
+            Self::Full => FULL_PRESET_EXCLUDED_ENTRIES.to_vec(),
+        }
+    }
 }
 
 pub struct Copier;
@@ -26,33 +94,409 @@ impl Copier {
         application: &mut Application,
         copy_options: &CopyOptions,
     ) -> Result<()> {
-        let mut entries = vec![
-            FileNamed::wildmatch("*.image").boxed(),
-            FileNamed::wildmatch("*.changes").boxed(),
-            FileNamed::wildmatch("*.sources").boxed(),
-            FileNamed::exact(Application::serialization_file_name()).boxed(),
-            FolderNamed::exact("gt-extra").boxed(),
-        ];
+        let workspace = application.workspace().to_path_buf();
 
-        if copy_options.include_app {
-            entries.extend(application.gtoolkit_app_entries());
+        // a copy of the workspace is useless without an image and its changes
+        for required in ["*.image", "*.changes"] {
+            FileNamed::wildmatch(required)
+                .within_path_buf(workspace.clone())
+                .find()?;
         }
 
-        let entries = entries
-            .into_iter()
-            .map(|each| each.within_path_buf(application.workspace().to_path_buf()))
-            .collect::<Vec<OneEntry>>();
-
         if !copy_options.destination.exists() {
             std::fs::create_dir_all(copy_options.destination.as_path())?;
         }
+        let destination =
+            to_absolute::canonicalize(&copy_options.destination).map_err(|error| {
+                InstallerError::CanonicalizeError(copy_options.destination.clone(), error)
+            })?;
+
+        let included_entries = Self::patterns(
+            copy_options.preset.included_entries(),
+            &copy_options.include,
+        );
+        let mut excluded_entries = Self::patterns(
+            copy_options.preset.excluded_entries(),
+            &copy_options.exclude,
+        );
 
-        for ref entry in entries {
-            entry.copy(copy_options.destination.as_path())?;
+        // never copy the destination into itself when it is within the workspace
+        let canonical_workspace = to_absolute::canonicalize(&workspace)
+            .map_err(|error| InstallerError::CanonicalizeError(workspace.clone(), error))?;
+        if let Ok(relative) = destination.strip_prefix(&canonical_workspace) {
+            excluded_entries.push(WildMatch::new(
+                &relative.to_string_lossy().replace('\\', "/"),
+            ));
         }
 
-        application.set_workspace(copy_options.destination.clone());
+        let mut app_entries = vec![];
+        for entry in application.gtoolkit_app_entries() {
+            let entry = entry.within_path_buf(workspace.clone());
+            if copy_options.include_app {
+                app_entries.push(entry.find()?);
+            } else if let Ok(path) = entry.find() {
+                app_entries.push(path);
+            }
+        }
+
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(&workspace)? {
+            let path = entry?.path();
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            // the app is copied only when requested
+            if app_entries.contains(&path) {
+                continue;
+            }
+
+            if included_entries
+                .iter()
+                .any(|pattern| pattern.matches(&name))
+            {
+                entries.push((path, Self::is_immutable(&name)));
+            }
+        }
+
+        if copy_options.include_app {
+            entries.extend(app_entries.into_iter().map(|entry| (entry, true)));
+        }
+
+        let mut copied_files = 0;
+        for (entry, is_immutable) in entries {
+            let link = if is_immutable {
+                copy_options.link
+            } else {
+                LinkMode::Copy
+            };
+            copied_files += Self::copy_entry(
+                &workspace,
+                &entry,
+                destination.as_path(),
+                link,
+                &excluded_entries,
+            )?;
+        }
+
+        if copied_files > 0 {
+            println!(
+                "{} file(s) could not be linked and were copied instead",
+                copied_files
+            );
+        }
+
+        application.set_workspace(destination);
+        if application.serialization_file().exists() {
+            application.serialize_into_file()?;
+        }
 
         Ok(())
     }
+
+    fn patterns(preset: Vec<&str>, extra: &[String]) -> Vec<WildMatch> {
+        preset
+            .into_iter()
+            .chain(extra.iter().map(|pattern| pattern.as_str()))
+            .map(WildMatch::new)
+            .collect()
+    }
+
+    fn is_immutable(name: &str) -> bool {
+        IMMUTABLE_ENTRIES
+            .iter()
+            .any(|pattern| WildMatch::new(pattern).matches(name))
+    }
+
+    /// Copies or links a file or a folder with all its content from the workspace into a destination folder,
+    /// skipping the files and folders whose path relative to the workspace matches an excluded pattern.
+    /// Symlinks are recreated as they are. Returns how many files could not be linked and were copied instead
+    fn copy_entry(
+        workspace: &Path,
+        entry: &Path,
+        destination: &Path,
+        link: LinkMode,
+        excluded_entries: &[WildMatch],
+    ) -> Result<usize> {
+        let mut copied_files = 0;
+
+        let is_excluded = |path: &Path| {
+            let relative = path
+                .strip_prefix(workspace)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            excluded_entries
+                .iter()
+                .any(|pattern| pattern.matches(&relative))
+        };
+
+        for each in WalkDir::new(entry)
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|each| !is_excluded(each.path()))
+        {
+            let each = each.map_err(std::io::Error::from)?;
+            let target =
+                destination.join(each.path().strip_prefix(workspace).unwrap_or(each.path()));
+
+            if each.file_type().is_dir() {
+                std::fs::create_dir_all(&target)?;
+                continue;
+            }
+
+            if target.symlink_metadata().is_ok() {
+                std::fs::remove_file(&target)?;
+            }
+
+            if each.path_is_symlink() {
+                copy_file_preserving_metadata(each.path(), &target)?;
+            } else if !Self::link_file(each.path(), &target, link)? && link != LinkMode::Copy {
+                copied_files += 1;
+            }
+        }
+
+        Ok(copied_files)
+    }
+
+    /// Links a file, falling back to copying it. Returns false if the file was copied
+    fn link_file(file: &Path, target: &Path, link: LinkMode) -> Result<bool> {
+        let linked = match link {
+            LinkMode::Hard => std::fs::hard_link(file, target).is_ok(),
+            LinkMode::Reflink => reflink_copy::reflink(file, target).is_ok(),
+            LinkMode::Copy => false,
+        };
+
+        if !linked {
+            copy_file_preserving_metadata(file, target)?;
+        }
+        Ok(linked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a workspace with an image, the App for Linux, the state of the image and logs
+    fn create_workspace(workspace: &Path) -> Application {
+        for file in [
+            "GlamorousToolkit.image",
+            "GlamorousToolkit.changes",
+            "Pharo.sources",
+            "gt-extra/feenk.png",
+            "bin/GlamorousToolkit",
+            "lib/libGlamorousToolkit.so",
+            "pharo-local/ombu-sessions/session.ombu",
+            "pharo-local/iceberg/feenkcom/gtoolkit/README.md",
+            "PharoDebug.log",
+            "crash.dmp",
+            "snapshots/snapshot/GlamorousToolkit.image",
+            "notes.txt",
+        ] {
+            let file = workspace.join(file);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, file.to_string_lossy().as_bytes()).unwrap();
+        }
+        let application = Application::for_tests(workspace);
+        application.serialize_into_file().unwrap();
+        application
+    }
+
+    /// The App is not copied unless requested, as its entries depend on the host platform
+    fn options(destination: &Path, args: &[&str], include_app: bool) -> CopyOptions {
+        let mut options = CopyOptions::parse_from(
+            ["copy-to", destination.to_str().unwrap()]
+                .iter()
+                .chain(args.iter()),
+        );
+        options.include_app = include_app;
+        options
+    }
+
+    /// Copies a new workspace and returns the files that were copied, relative to the destination
+    async fn copy(
+        args: &[&str],
+        include_app: bool,
+    ) -> (tempfile::TempDir, Application, Vec<String>) {
+        let directory = tempfile::tempdir().unwrap();
+        let mut application = create_workspace(&directory.path().join("workspace"));
+        let destination = directory.path().join("copy");
+
+        Copier::new()
+            .copy(&mut application, &options(&destination, args, include_app))
+            .await
+            .unwrap();
+
+        let mut files = WalkDir::new(&destination)
+            .into_iter()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(&destination)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect::<Vec<String>>();
+        files.sort();
+        (directory, application, files)
+    }
+
+    #[tokio::test]
+    async fn minimal_preset_copies_the_image() {
+        let (_directory, _, files) = copy(&["--preset", "minimal"], false).await;
+        assert_eq!(
+            files,
+            vec![
+                "GlamorousToolkit.changes",
+                "GlamorousToolkit.image",
+                "Pharo.sources",
+                SERIALIZATION_FILE,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn default_preset_copies_gt_extra() {
+        let (_directory, _, files) = copy(&[], false).await;
+        assert_eq!(
+            files,
+            vec![
+                "GlamorousToolkit.changes",
+                "GlamorousToolkit.image",
+                "Pharo.sources",
+                "gt-extra/feenk.png",
+                SERIALIZATION_FILE,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn full_preset_copies_everything_except_logs_and_snapshots() {
+        let (_directory, _, files) = copy(&["--preset", "full"], false).await;
+        assert_eq!(
+            files,
+            vec![
+                "GlamorousToolkit.changes",
+                "GlamorousToolkit.image",
+                "Pharo.sources",
+                "gt-extra/feenk.png",
+                SERIALIZATION_FILE,
+                "notes.txt",
+                "pharo-local/iceberg/feenkcom/gtoolkit/README.md",
+                "pharo-local/ombu-sessions/session.ombu",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn includes_and_excludes_patterns() {
+        let (_directory, _, files) = copy(
+            &[
+                "--preset",
+                "minimal",
+                "--include",
+                "pharo-local",
+                "--include",
+                "*.txt",
+                "--exclude",
+                "pharo-local/iceberg/*",
+                "--exclude",
+                "*.sources",
+            ],
+            false,
+        )
+        .await;
+        assert_eq!(
+            files,
+            vec![
+                "GlamorousToolkit.changes",
+                "GlamorousToolkit.image",
+                SERIALIZATION_FILE,
+                "notes.txt",
+                "pharo-local/ombu-sessions/session.ombu",
+            ]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn copies_the_app_when_requested() {
+        let (_directory, _, files) = copy(&["--preset", "minimal"], true).await;
+        assert!(files.contains(&"bin/GlamorousToolkit".to_string()));
+        assert!(files.contains(&"lib/libGlamorousToolkit.so".to_string()));
+    }
+
+    #[tokio::test]
+    async fn persists_the_new_workspace() {
+        let (directory, application, _) = copy(&[], false).await;
+        let destination = to_absolute::canonicalize(directory.path().join("copy")).unwrap();
+        assert_eq!(application.workspace(), destination.as_path());
+
+        let serialized: Application = serde_yaml::from_str(
+            &std::fs::read_to_string(destination.join(SERIALIZATION_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(serialized.workspace(), destination.as_path());
+
+        // the original workspace keeps its own state
+        let original: Application = serde_yaml::from_str(
+            &std::fs::read_to_string(directory.path().join("workspace").join(SERIALIZATION_FILE))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(original.workspace(), directory.path().join("workspace"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hard_links_only_immutable_entries() {
+        use std::os::unix::fs::MetadataExt;
+
+        let (directory, _, _) = copy(&["--link", "hard"], false).await;
+        let inode = |file: &str| {
+            std::fs::metadata(directory.path().join(file))
+                .unwrap()
+                .ino()
+        };
+
+        for linked in ["Pharo.sources", "gt-extra/feenk.png"] {
+            assert_eq!(
+                inode(&format!("workspace/{}", linked)),
+                inode(&format!("copy/{}", linked))
+            );
+        }
+        for copied in ["GlamorousToolkit.image", "GlamorousToolkit.changes"] {
+            assert_ne!(
+                inode(&format!("workspace/{}", copied)),
+                inode(&format!("copy/{}", copied))
+            );
+        }
+    }
+
+    #[test]
+    fn copies_files_that_can_not_be_linked() {
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("Pharo.sources");
+        std::fs::write(&file, "sources").unwrap();
+
+        // a hard link can not replace an existing file
+        let target = directory.path().join("existing.sources");
+        std::fs::write(&target, "outdated").unwrap();
+        assert!(!Copier::link_file(&file, &target, LinkMode::Hard).unwrap());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "sources");
+
+        // the temporary folder may or may not support copy-on-write
+        let target = directory.path().join("reflinked.sources");
+        Copier::link_file(&file, &target, LinkMode::Reflink).unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "sources");
+
+        let target = directory.path().join("copied.sources");
+        assert!(!Copier::link_file(&file, &target, LinkMode::Copy).unwrap());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "sources");
+    }
 }
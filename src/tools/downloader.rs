@@ -4,10 +4,14 @@ use feenk_download_auth_client::{
     download_release_asset_with_env_auth, EnvDownloadRequest, InstallationTokenSource,
 };
 use std::env;
+use std::path::PathBuf;
 use unzipper::{FileToUnzip, FilesToUnzip};
 
 use crate::options::VM_PRO_REPOSITORY_NAME;
-use crate::{Application, InstallerError, PlatformOS, Result, DOWNLOADING, EXTRACTING};
+use crate::{
+    signature_path, Application, InstallerError, PlatformOS, Result, SignatureVerifier, CHECKING,
+    DOWNLOADING, EXTRACTING,
+};
 
 const FEENK_DOWNLOAD_AUTH_SERVER_URL: &str = "https://dl-auth.feenk.com";
 const FEENK_CUSTOMER_ID_ENV: &str = "FEENK_CUSTOMER_ID";
@@ -17,13 +21,25 @@ pub struct Downloader {
     silent: bool,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, ArgEnum)]
+#[serde(rename_all = "lowercase")]
 pub enum CustomerLevel {
+    #[default]
     Auto,
     Regular,
     Pro,
 }
 
+impl CustomerLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CustomerLevel::Auto => "auto",
+            CustomerLevel::Regular => "regular",
+            CustomerLevel::Pro => "pro",
+        }
+    }
+}
+
 #[derive(Parser, Debug, Clone)]
 pub struct DownloadOptions {
     #[clap(subcommand)]
@@ -106,6 +122,20 @@ impl Downloader {
         }
     }
 
+    /// Resolves `auto` to the customer level of the VM that would be downloaded
+    pub fn resolve_customer_level(customer_level: CustomerLevel) -> Result<CustomerLevel> {
+        match customer_level {
+            CustomerLevel::Auto => {
+                if Self::should_download_pro_vm(customer_level)? {
+                    Ok(CustomerLevel::Pro)
+                } else {
+                    Ok(CustomerLevel::Regular)
+                }
+            }
+            _ => Ok(customer_level),
+        }
+    }
+
     pub fn files_to_download(
         application: &Application,
         target: PlatformOS,
@@ -197,31 +227,92 @@ impl Downloader {
 
         if Self::should_download_pro_vm(customer_level)? {
             let asset_name = application.gtoolkit_pro_app_file_name_for_target(target);
-            let tag = format!("v{}", application.app_version());
             let output_path = gtoolkit_vm.path();
 
             if let Some(output_directory) = output_path.parent() {
                 std::fs::create_dir_all(output_directory)?;
             }
 
-            download_release_asset_with_env_auth(EnvDownloadRequest {
-                token_source: InstallationTokenSource::customer_env(
-                    FEENK_DOWNLOAD_AUTH_SERVER_URL,
-                    FEENK_CUSTOMER_ID_ENV,
-                    FEENK_CUSTOMER_KEY_ENV,
-                ),
-                repo: VM_PRO_REPOSITORY_NAME.to_string(),
-                github_owner: None,
-                tag: Some(tag),
-                asset_name,
-                output_path,
-            })
-            .await?;
-
-            Ok(())
+            Self::download_pro_vm_asset(application, asset_name, output_path).await?;
         } else {
             FilesToDownload::new().add(gtoolkit_vm).download().await?;
-            Ok(())
         }
+
+        self.verify_glamorous_toolkit_vm_archive(application, target, customer_level)
+            .await
+    }
+
+    /// When a public key is configured in the FEENK_PUBLIC_KEY environment variable,
+    /// downloads the signature of the GlamorousToolkit App archive and verifies the archive with it
+    pub async fn verify_glamorous_toolkit_vm_archive(
+        &self,
+        application: &Application,
+        target: PlatformOS,
+        customer_level: CustomerLevel,
+    ) -> Result<()> {
+        if application.has_explicit_app_cli_binary() {
+            return Ok(());
+        }
+
+        let verifier = match SignatureVerifier::from_key_or_env(None)? {
+            Some(verifier) => verifier,
+            None => return Ok(()),
+        };
+
+        let gtoolkit_vm = Self::gtoolkit_vm_to_download(application, target);
+        let archive = gtoolkit_vm.path();
+        let signature = signature_path(&archive);
+
+        if Self::should_download_pro_vm(customer_level)? {
+            let asset_name = format!(
+                "{}.sig",
+                application.gtoolkit_pro_app_file_name_for_target(target)
+            );
+            Self::download_pro_vm_asset(application, asset_name, signature.clone()).await?;
+        } else {
+            let signature_name = signature
+                .file_name()
+                .ok_or_else(|| InstallerError::FailedToReadFileName(signature.clone()))?
+                .to_string_lossy()
+                .to_string();
+            FilesToDownload::new()
+                .add(FileToDownload::new(
+                    format!("{}.sig", application.gtoolkit_app_url_for_target(target)),
+                    application.gtoolkit_app_location(target),
+                    signature_name,
+                ))
+                .download()
+                .await?;
+        }
+
+        if !self.silent {
+            println!(
+                "{}Verifying signature of GlamorousToolkit App (v{})...",
+                CHECKING,
+                application.app_version().to_string()
+            );
+        }
+        verifier.verify(&archive, &signature)
+    }
+
+    async fn download_pro_vm_asset(
+        application: &Application,
+        asset_name: String,
+        output_path: PathBuf,
+    ) -> Result<()> {
+        download_release_asset_with_env_auth(EnvDownloadRequest {
+            token_source: InstallationTokenSource::customer_env(
+                FEENK_DOWNLOAD_AUTH_SERVER_URL,
+                FEENK_CUSTOMER_ID_ENV,
+                FEENK_CUSTOMER_KEY_ENV,
+            ),
+            repo: VM_PRO_REPOSITORY_NAME.to_string(),
+            github_owner: None,
+            tag: Some(format!("v{}", application.app_version())),
+            asset_name,
+            output_path,
+        })
+        .await?;
+        Ok(())
     }
 }
@@ -0,0 +1,237 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::time::Duration;
+
+use indicatif::HumanDuration;
+use parse_duration::parse as duration_parse;
+use wildmatch::WildMatch;
+
+use crate::{InstallerError, JUnitReport, JUnitTestCase, JUnitTestSuite, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestStage {
+    Examples,
+    Tests,
+    Slides,
+    Report(String),
+}
+
+impl Display for TestStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Examples => f.write_str("examples"),
+            Self::Tests => f.write_str("tests"),
+            Self::Slides => f.write_str("slides"),
+            Self::Report(report) => write!(f, "{} report", report),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StageDuration {
+    stage: TestStage,
+    duration: Duration,
+    results: JUnitReport,
+}
+
+/// Collects the time spent in each test stage together with the timing
+/// of the individual examples and tests reported by the image
+#[derive(Debug, Clone, Default)]
+pub struct DurationReport {
+    stages: Vec<StageDuration>,
+}
+
+impl DurationReport {
+    pub fn new() -> Self {
+        Self { stages: vec![] }
+    }
+
+    pub fn add_stage(&mut self, stage: TestStage, duration: Duration, results: JUnitReport) {
+        self.stages.push(StageDuration {
+            stage,
+            duration,
+            results,
+        });
+    }
+
+    /// Returns the test results of all stages
+    pub fn suites(&self) -> impl Iterator<Item = &JUnitTestSuite> {
+        self.stages.iter().flat_map(|stage| stage.results.suites())
+    }
+
+    /// Returns packages with the total time spent testing them across all stages, the slowest first
+    pub fn slowest_packages(&self) -> Vec<(String, Duration)> {
+        let mut packages: BTreeMap<String, Duration> = BTreeMap::new();
+        for suite in self.suites() {
+            *packages.entry(suite.name().to_string()).or_default() += suite.duration();
+        }
+
+        let mut packages = packages.into_iter().collect::<Vec<(String, Duration)>>();
+        packages.sort_by_key(|(_, duration)| Reverse(*duration));
+        packages
+    }
+
+    /// Returns examples and tests together with their package, the slowest first
+    pub fn slowest_test_cases(&self) -> Vec<(&JUnitTestSuite, &JUnitTestCase)> {
+        let mut test_cases = self
+            .suites()
+            .flat_map(|suite| suite.test_cases().iter().map(move |each| (suite, each)))
+            .collect::<Vec<(&JUnitTestSuite, &JUnitTestCase)>>();
+        test_cases.sort_by_key(|(_, test_case)| Reverse(test_case.duration()));
+        test_cases
+    }
+
+    pub fn print(&self, amount_of_slowest: usize) {
+        println!("Time per stage:");
+        for stage in &self.stages {
+            println!(
+                "  {:<24} {}",
+                stage.stage.to_string(),
+                HumanDuration(stage.duration)
+            );
+        }
+
+        println!("Slowest packages:");
+        for (index, (package, duration)) in self
+            .slowest_packages()
+            .iter()
+            .take(amount_of_slowest)
+            .enumerate()
+        {
+            println!(
+                "  {:>3}. {} {}",
+                index + 1,
+                package,
+                HumanDuration(*duration)
+            );
+        }
+
+        println!("Slowest examples:");
+        for (index, (suite, test_case)) in self
+            .slowest_test_cases()
+            .iter()
+            .take(amount_of_slowest)
+            .enumerate()
+        {
+            println!(
+                "  {:>3}. {} ({}) {}",
+                index + 1,
+                test_case.full_name(),
+                suite.name(),
+                HumanDuration(test_case.duration())
+            );
+        }
+    }
+}
+
+/// A budget is either a human readable duration or an amount of seconds
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum BudgetValue {
+    Seconds(u64),
+    Text(String),
+}
+
+/// Maps package names or glob patterns to the maximal amount of time testing them may take.
+/// Read from a .yaml file with durations or plain numbers of seconds, for example:
+/// ```yaml
+/// GToolkit-Examples: 10 minutes
+/// Brick-*: 2m 30s
+/// Lepiter-*: 300
+/// ```
+#[derive(Debug, Clone)]
+pub struct TimeBudget {
+    budgets: Vec<(String, Duration)>,
+}
+
+impl TimeBudget {
+    pub fn from_file(file: impl AsRef<Path>) -> Result<Self> {
+        let file = file.as_ref();
+        let content = std::fs::read_to_string(file)
+            .map_err(|error| InstallerError::TimeBudgetReadError(file.to_path_buf(), error))?;
+        let budgets: BTreeMap<String, BudgetValue> = serde_yaml::from_str(content.as_str())?;
+
+        let budgets = budgets
+            .into_iter()
+            .map(|(package, budget)| match budget {
+                BudgetValue::Seconds(seconds) => Ok((package, Duration::from_secs(seconds))),
+                BudgetValue::Text(budget) => duration_parse(budget.as_str())
+                    .map(|duration| (package.clone(), duration))
+                    .map_err(|error| InstallerError::TimeBudgetParseError(package, budget, error)),
+            })
+            .collect::<Result<Vec<(String, Duration)>>>()?;
+
+        Ok(Self { budgets })
+    }
+
+    /// Fails if any of the packages in the report took longer than its budget.
+    /// A package matching multiple patterns must fit into the smallest budget
+    pub fn check(&self, report: &DurationReport) -> Result<()> {
+        let exceeded = report
+            .slowest_packages()
+            .into_iter()
+            .filter_map(|(package, duration)| {
+                self.budgets
+                    .iter()
+                    .filter(|(pattern, _)| WildMatch::new(pattern).matches(package.as_str()))
+                    .map(|(_, budget)| *budget)
+                    .min()
+                    .filter(|budget| duration > *budget)
+                    .map(|budget| {
+                        format!(
+                            "  {} took {}, the budget is {}",
+                            package,
+                            HumanDuration(duration),
+                            HumanDuration(budget)
+                        )
+                    })
+            })
+            .collect::<Vec<String>>();
+
+        if exceeded.is_empty() {
+            Ok(())
+        } else {
+            InstallerError::TimeBudgetExceeded(exceeded.join("\n")).into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_budgets_in_seconds_and_durations() {
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("budget.yaml");
+        std::fs::write(
+            &file,
+            "GToolkit-Examples: 10 minutes\nBrick-*: 2m 30s\nLepiter-*: 300\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            TimeBudget::from_file(&file).unwrap().budgets,
+            vec![
+                ("Brick-*".to_string(), Duration::from_secs(150)),
+                ("GToolkit-Examples".to_string(), Duration::from_secs(600)),
+                ("Lepiter-*".to_string(), Duration::from_secs(300)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_a_malformed_budget() {
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("budget.yaml");
+        std::fs::write(&file, "Brick-*: a while\n").unwrap();
+
+        assert!(matches!(
+            TimeBudget::from_file(&file),
+            Err(InstallerError::TimeBudgetParseError(package, budget, _))
+                if package == "Brick-*" && budget == "a while"
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::{Application, InstallerError, Result, TestHistory, TestOutcome, TestRun};
+
+#[derive(Parser, Debug, Clone)]
+pub struct TestHistoryOptions {
+    /// Path to the test history file. Defaults to `test-history.jsonl` in the workspace
+    #[clap(long, parse(from_os_str))]
+    pub history_file: Option<PathBuf>,
+    #[clap(subcommand)]
+    pub query: TestHistoryQuery,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub enum TestHistoryQuery {
+    /// Print the outcome of an example or test in every recorded run, the oldest first
+    Trend(ExampleQueryOptions),
+    /// Print the run since which an example or test has been failing
+    FirstFailure(ExampleQueryOptions),
+    /// Print the pass rate of all examples and tests per image version
+    PassRate,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ExampleQueryOptions {
+    /// A full name of the example or test in a form of `Class>>#selector`
+    pub name: String,
+    /// Only consider a given amount of the most recent runs, at least one
+    #[clap(long)]
+    pub last: Option<NonZeroUsize>,
+}
+
+pub struct Historian;
+
+impl Historian {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn query(
+        &self,
+        application: &Application,
+        history_options: &TestHistoryOptions,
+    ) -> Result<()> {
+        let history =
+            TestHistory::for_application(application, history_options.history_file.as_deref());
+        let runs = history.runs()?;
+
+        match &history_options.query {
+            TestHistoryQuery::Trend(query_options) => Self::print_trend(&runs, query_options),
+            TestHistoryQuery::FirstFailure(query_options) => {
+                Self::print_first_failure(&runs, query_options)
+            }
+            TestHistoryQuery::PassRate => {
+                Self::print_pass_rate(&runs);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the runs that include a given example or test together with its outcome, the oldest first
+    fn runs_with_example<'a>(
+        runs: &'a [TestRun],
+        query_options: &ExampleQueryOptions,
+    ) -> Result<Vec<(&'a TestRun, TestOutcome)>> {
+        let mut runs = runs
+            .iter()
+            .filter_map(|run| {
+                run.result_of(&query_options.name)
+                    .map(|result| (run, result.outcome))
+            })
+            .collect::<Vec<(&TestRun, TestOutcome)>>();
+
+        if runs.is_empty() {
+            return InstallerError::TestHistoryExampleNotFound(query_options.name.clone()).into();
+        }
+
+        if let Some(last) = query_options.last {
+            runs = runs.split_off(runs.len().saturating_sub(last.get()));
+        }
+
+        Ok(runs)
+    }
+
+    fn print_trend(runs: &[TestRun], query_options: &ExampleQueryOptions) -> Result<()> {
+        for (run, outcome) in Self::runs_with_example(runs, query_options)? {
+            println!("{} {}", run.description(), outcome);
+        }
+        Ok(())
+    }
+
+    fn print_first_failure(runs: &[TestRun], query_options: &ExampleQueryOptions) -> Result<()> {
+        let runs = Self::runs_with_example(runs, query_options)?;
+
+        let failing_runs = runs
+            .iter()
+            .rev()
+            .take_while(|(_, outcome)| outcome.is_failure())
+            .count();
+
+        if failing_runs == 0 {
+            let (latest_run, outcome) = runs.last().expect("There is at least one run");
+            println!(
+                "{} {} in the most recent run {}",
+                &query_options.name,
+                outcome,
+                latest_run.description()
+            );
+            return Ok(());
+        }
+
+        let first_failing_index = runs.len() - failing_runs;
+        let (first_failing_run, _) = runs[first_failing_index];
+        println!(
+            "{} has been failing for {} run(s), since {}",
+            &query_options.name,
+            failing_runs,
+            first_failing_run.description()
+        );
+        if let Some(message) = first_failing_run
+            .result_of(&query_options.name)
+            .and_then(|result| result.message.as_ref())
+        {
+            println!("  {}", message);
+        }
+        if first_failing_index > 0 {
+            let (last_passing_run, _) = runs[first_failing_index - 1];
+            println!(
+                "Last run without a failure: {}",
+                last_passing_run.description()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn print_pass_rate(runs: &[TestRun]) {
+        // image versions in the order they were first tested
+        let mut versions: Vec<(String, usize, usize, usize)> = vec![];
+
+        for run in runs {
+            let index = match versions
+                .iter()
+                .position(|(version, _, _, _)| version == &run.image_version)
+            {
+                Some(index) => index,
+                None => {
+                    versions.push((run.image_version.clone(), 0, 0, 0));
+                    versions.len() - 1
+                }
+            };
+
+            let (_, amount_of_runs, passed, total) = &mut versions[index];
+            *amount_of_runs += 1;
+            for result in &run.results {
+                match result.outcome {
+                    TestOutcome::Passed => {
+                        *passed += 1;
+                        *total += 1;
+                    }
+                    TestOutcome::Failed | TestOutcome::Errored => *total += 1,
+                    TestOutcome::Skipped => {}
+                }
+            }
+        }
+
+        for (version, amount_of_runs, passed, total) in versions {
+            let rate = if total > 0 {
+                passed as f64 * 100.0 / total as f64
+            } else {
+                100.0
+            };
+            println!(
+                "v{} {:.1}% ({}/{}) in {} run(s)",
+                version, rate, passed, total, amount_of_runs
+            );
+        }
+    }
+}
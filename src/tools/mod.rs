@@ -3,28 +3,45 @@ mod checker;
 mod cleaner;
 mod copier;
 mod downloader;
+mod durations;
+mod history;
 mod package;
+mod publisher;
 mod release;
 mod renamer;
 mod setup;
+mod snapshotter;
 mod starter;
 mod tentative;
 mod tester;
+mod verifier;
 
 use console::Emoji;
 
 pub use self::downloader::{CustomerLevel, DownloadOptions, Downloader};
-pub use builder::{BuildOptions, Builder, Loader, LocalBuildOptions, ReleaseBuildOptions};
+pub use builder::{
+    BuildOptions, Builder, Loader, LoaderVersionInfo, LocalBuildOptions, ReleaseBuildOptions,
+};
 pub use checker::Checker;
-pub use cleaner::Cleaner;
-pub use copier::{Copier, CopyOptions};
+pub use cleaner::{CleanUpOptions, CleanUpProfile, Cleaner};
+pub use copier::{Copier, CopyOptions, CopyPreset, LinkMode};
+pub use durations::{DurationReport, TestStage, TimeBudget};
+pub use history::{Historian, TestHistoryOptions};
 pub use package::Package;
+pub use publisher::{
+    GitHubPublishOptions, PublishDestination, PublishOptions, Publisher, S3PublishOptions,
+};
 pub use release::{Release, ReleaseOptions, ReleaserOptions};
-pub use renamer::{RenameOptions, Renamer};
+pub use renamer::{RenameMode, RenameOptions, Renamer};
 pub use setup::{Setup, SetupOptions, SetupTarget};
+pub use snapshotter::{
+    CreateSnapshotOptions, DeleteSnapshotOptions, RestoreSnapshotOptions, SnapshotCommand,
+    SnapshotOptions, Snapshotter,
+};
 pub use starter::{StartOptions, Starter};
 pub use tentative::{Tentative, TentativeOptions};
-pub use tester::{TestOptions, Tester};
+pub use tester::{ListPackagesOptions, TestOptions, TestablePackage, Tester};
+pub use verifier::{PackageVerifier, VerifyPackageOptions, VerifySignatureOptions};
 
 pub static CHECKING: Emoji<'_, '_> = Emoji("🔍 ", "");
 pub static DOWNLOADING: Emoji<'_, '_> = Emoji("📥 ", "");
@@ -32,4 +49,5 @@ pub static EXTRACTING: Emoji<'_, '_> = Emoji("📦 ", "");
 pub static MOVING: Emoji<'_, '_> = Emoji("🚚 ", "");
 pub static CREATING: Emoji<'_, '_> = Emoji("📝 ", "");
 pub static BUILDING: Emoji<'_, '_> = Emoji("🔨 ", "");
+pub static UPLOADING: Emoji<'_, '_> = Emoji("📤 ", "");
 pub static SPARKLE: Emoji<'_, '_> = Emoji("✨ ", ":-)");
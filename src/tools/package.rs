@@ -8,37 +8,26 @@ impl Package {
         Self::gtoolkit_app_entries_for_target(application, application.host_platform())
     }
 
+    /// Returns the names of the GlamorousToolkit App folders that are packaged for a given target
+    pub fn gtoolkit_app_entry_names_for_target(target: PlatformOS) -> Vec<&'static str> {
+        match target {
+            PlatformOS::MacOSX8664 => vec!["GlamorousToolkit.app"],
+            PlatformOS::MacOSAarch64 => vec!["GlamorousToolkit.app"],
+            PlatformOS::WindowsX8664 => vec!["bin"],
+            PlatformOS::WindowsAarch64 => vec!["bin"],
+            PlatformOS::LinuxX8664 => vec!["bin", "lib"],
+            PlatformOS::LinuxAarch64 => vec!["bin", "lib"],
+            PlatformOS::AndroidAarch64 => vec!["lib"],
+        }
+    }
+
     pub fn gtoolkit_app_entries_for_target(
         application: &Application,
         target: PlatformOS,
     ) -> Vec<OneEntry> {
-        let folders = match target {
-            PlatformOS::MacOSX8664 => {
-                vec![FolderNamed::exact("GlamorousToolkit.app")]
-            }
-            PlatformOS::MacOSAarch64 => {
-                vec![FolderNamed::exact("GlamorousToolkit.app")]
-            }
-            PlatformOS::WindowsX8664 => {
-                vec![FolderNamed::exact("bin")]
-            }
-            PlatformOS::WindowsAarch64 => {
-                vec![FolderNamed::exact("bin")]
-            }
-            PlatformOS::LinuxX8664 => {
-                vec![FolderNamed::exact("bin"), FolderNamed::exact("lib")]
-            }
-            PlatformOS::LinuxAarch64 => {
-                vec![FolderNamed::exact("bin"), FolderNamed::exact("lib")]
-            }
-            PlatformOS::AndroidAarch64 => {
-                vec![FolderNamed::exact("lib")]
-            }
-        };
-
-        folders
+        Self::gtoolkit_app_entry_names_for_target(target)
             .into_iter()
-            .map(|each| each.within(application.gtoolkit_app_location(target)))
+            .map(|each| FolderNamed::exact(each).within(application.gtoolkit_app_location(target)))
             .collect::<Vec<OneEntry>>()
     }
 }
@@ -0,0 +1,220 @@
+use std::path::{Path, PathBuf};
+
+use clap::{ArgEnum, Parser};
+use url::Url;
+
+use crate::{
+    sha256_of_file, signature_path, Application, GitHubRelease, InstallerError, ManifestFormat,
+    ReleaseManifest, Result, S3Bucket, CHECKSUMS_FILE, GITHUB_API_URL, GTOOLKIT_REPOSITORY_NAME,
+    GTOOLKIT_REPOSITORY_OWNER, RELEASE_NOTES_FILE, RELEASE_NOTES_MARKDOWN_FILE, SPARKLE, UPLOADING,
+};
+
+#[derive(Parser, Debug, Clone)]
+pub struct PublishOptions {
+    #[clap(subcommand)]
+    pub destination: PublishDestination,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub enum PublishDestination {
+    /// Uploads the packages as assets of a GitHub release, creating the release if it does not exist.
+    /// Requires a token in the GITHUB_TOKEN environment variable
+    #[clap(name = "github")]
+    GitHub(GitHubPublishOptions),
+    /// Uploads the packages to a bucket of an S3-compatible object store.
+    /// Requires the credentials in the AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY environment variables
+    S3(S3PublishOptions),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct GitHubPublishOptions {
+    /// Packages created by `package-release`. Their manifests, the SHA256SUMS and the release notes
+    /// next to them are published as well
+    #[clap(parse(from_os_str), required = true)]
+    pub packages: Vec<PathBuf>,
+    /// The owner of the repository to publish to
+    #[clap(long, default_value = GTOOLKIT_REPOSITORY_OWNER)]
+    pub owner: String,
+    /// The repository to publish to
+    #[clap(long, default_value = GTOOLKIT_REPOSITORY_NAME)]
+    pub repository: String,
+    /// The tag of the release. Defaults to v{{version}} of the image in the workspace
+    #[clap(long)]
+    pub tag: Option<String>,
+    /// The base url of the GitHub API, for example of a GitHub Enterprise server
+    #[clap(long, default_value = GITHUB_API_URL)]
+    pub api_url: Url,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct S3PublishOptions {
+    /// Packages created by `package-release`. Their manifests, the SHA256SUMS and the release notes
+    /// next to them are published as well
+    #[clap(parse(from_os_str), required = true)]
+    pub packages: Vec<PathBuf>,
+    /// The endpoint of the object store, for example https://s3.eu-central-1.amazonaws.com or http://localhost:9000
+    #[clap(long)]
+    pub endpoint: Url,
+    /// The bucket to upload to
+    #[clap(long)]
+    pub bucket: String,
+    /// A prefix of the keys of the uploaded objects, for example "releases/v1.0.0"
+    #[clap(long, default_value = "")]
+    pub prefix: String,
+    /// The region of the bucket
+    #[clap(long, default_value = "us-east-1")]
+    pub region: String,
+}
+
+pub struct Publisher;
+
+impl Publisher {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Uploads the packages and the files that describe them.
+    /// Files that were already uploaded with the same checksum are skipped, changed files are replaced
+    pub async fn publish(
+        &self,
+        application: &Application,
+        publish_options: &PublishOptions,
+    ) -> Result<()> {
+        match &publish_options.destination {
+            PublishDestination::GitHub(options) => {
+                self.publish_to_github(application, options).await
+            }
+            PublishDestination::S3(options) => self.publish_to_s3(options).await,
+        }
+    }
+
+    async fn publish_to_github(
+        &self,
+        application: &Application,
+        options: &GitHubPublishOptions,
+    ) -> Result<()> {
+        let files = Self::files_to_publish(&options.packages)?;
+        let tag = options
+            .tag
+            .clone()
+            .unwrap_or_else(|| format!("v{}", application.image_version().to_string()));
+
+        let github = GitHubRelease::from_env(
+            options.api_url.clone(),
+            &options.owner,
+            &options.repository,
+            &tag,
+        )?;
+        let release = github.find_or_create().await?;
+
+        for file in &files {
+            let name = Self::file_name(file)?;
+            let sha256 = sha256_of_file(file)?;
+
+            if let Some(asset) = release.assets.iter().find(|asset| asset.name == name) {
+                if github.asset_sha256(asset).await? == sha256 {
+                    println!("{} is up to date", name);
+                    continue;
+                }
+                github.delete_asset(asset).await?;
+            }
+
+            println!("{}Uploading {}...", UPLOADING, name);
+            github.upload_asset(&release, &name, file).await?;
+        }
+
+        println!(
+            "{}Published {} file(s) to {}/{} {}",
+            SPARKLE,
+            files.len(),
+            &options.owner,
+            &options.repository,
+            &tag
+        );
+        Ok(())
+    }
+
+    async fn publish_to_s3(&self, options: &S3PublishOptions) -> Result<()> {
+        let files = Self::files_to_publish(&options.packages)?;
+        let bucket =
+            S3Bucket::from_env(options.endpoint.clone(), &options.bucket, &options.region)?;
+        let prefix = options.prefix.trim_matches('/');
+
+        for file in &files {
+            let name = Self::file_name(file)?;
+            let key = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            let sha256 = sha256_of_file(file)?;
+
+            if bucket.object_sha256(&key).await?.as_deref() == Some(sha256.as_str()) {
+                println!("{} is up to date", key);
+                continue;
+            }
+
+            println!("{}Uploading {}...", UPLOADING, key);
+            bucket.put_file(&key, file, &sha256).await?;
+        }
+
+        println!(
+            "{}Published {} file(s) to {}",
+            SPARKLE,
+            files.len(),
+            &options.bucket
+        );
+        Ok(())
+    }
+
+    /// Returns the packages followed by their signatures and manifests,
+    /// and the SHA256SUMS and release notes of the directories they are in
+    fn files_to_publish(packages: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        let mut directories: Vec<PathBuf> = vec![];
+
+        for package in packages {
+            if !package.is_file() {
+                return InstallerError::PackageNotFound(package.clone()).into();
+            }
+            files.push(package.clone());
+            files.push(signature_path(package));
+            for format in ManifestFormat::value_variants() {
+                files.push(ReleaseManifest::manifest_path(package, *format));
+            }
+
+            let directory = package
+                .parent()
+                .filter(|directory| !directory.as_os_str().is_empty())
+                .map(|directory| directory.to_path_buf())
+                .unwrap_or_else(|| PathBuf::from("."));
+            if !directories.contains(&directory) {
+                directories.push(directory);
+            }
+        }
+
+        for directory in directories {
+            for each in [
+                CHECKSUMS_FILE,
+                RELEASE_NOTES_MARKDOWN_FILE,
+                RELEASE_NOTES_FILE,
+            ] {
+                files.push(directory.join(each));
+            }
+        }
+
+        let mut existing: Vec<PathBuf> = vec![];
+        for file in files.into_iter().filter(|file| file.is_file()) {
+            if !existing.contains(&file) {
+                existing.push(file);
+            }
+        }
+        Ok(existing)
+    }
+
+    fn file_name(file: &Path) -> Result<String> {
+        file.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| InstallerError::FailedToReadFileName(file.to_path_buf()))
+    }
+}
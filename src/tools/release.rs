@@ -2,29 +2,70 @@ use std::fs::File;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use clap::{ArgEnum, Parser};
 use feenk_releaser::VersionBump;
-use file_matcher::FileNamed;
+use file_matcher::{FileNamed, FilesNamed, FolderNamed, OneEntry, OneEntryCopier};
+use futures::future::try_join_all;
 use zipper::ToZip;
 
 use crate::{
-    Application, CustomerLevel, Downloader, ExecutableSmalltalk, Package, PlatformOS, Result,
-    SmalltalkCommand,
+    Application, ArchiveFormat, Checksums, CustomerLevel, Downloader, ExecutableSmalltalk,
+    GToolkit, InstallerError, LoadedRepository, ManifestFile, ManifestFormat, Package,
+    PathTemplate, PlatformOS, ReleaseManifest, ReleaseNotes, Reproducible, Result, Sbom,
+    SbomFormat, Signer, SmalltalkCommand, TemplateVariables, ToDebian, ToReproducibleZip, ToTar,
+    DEBIAN_INSTALLATION_DIRECTORY, RELEASE_NOTES_FILE,
 };
 
 #[derive(Parser, Debug, Clone)]
 pub struct ReleaseOptions {
-    /// Path to the .zip with the release image build. Supports mustache syntax to inject various release related information.
+    /// Path to the archive with the release image build. Supports mustache syntax to inject various release related information.
     /// For example: "GlamorousToolkit-{{os}}-{{arch}}-v{{version}}.zip"
     ///
+    /// The archive format is chosen based on the extension: `.zip`, `.tar.gz` or `.tar.zst`.
+    /// Tar archives preserve file permissions and symlinks.
+    /// Linux releases can also be packaged as a Debian package (`.deb`) that installs into /opt/glamoroustoolkit
+    /// and Android releases are always packaged as an `.apk`
+    ///
     /// The following properties are supported:
     /// - {{version}} - the release version in a form of X.Y.Z
+    /// - {{app_version}} - the version of the GlamorousToolkit App in a form of X.Y.Z
     /// - {{os}} - the OS we release for. (`MacOS`, `Linux`, `Windows`, `Android`)
     /// - {{arch}} - the target release architecture. (`x86_64`, `aarch64`)
+    /// - {{target}} - the full target triple, for example `x86_64-unknown-linux-gnu`
+    /// - {{date}} - the current date in a form of YYYY-MM-DD
+    /// - {{customer_level}} - the customer level of the packaged App. (`regular`, `pro`)
+    /// - {{image_name}} - the name of the image without extension
+    /// - {{build_id}} - a git-describe-like build identifier: vX.Y.Z[-BUILD_NUMBER][-gCOMMIT]
     #[clap(parse(from_os_str), verbatim_doc_comment)]
     pub release: PathBuf,
-    #[clap(long, arg_enum)]
-    pub target: Option<PlatformOS>,
+    /// Targets to package the release for. Can be specified multiple times. Defaults to the host platform
+    #[clap(long = "target", arg_enum, multiple_occurrences = true)]
+    pub targets: Vec<PlatformOS>,
+    /// Package the release for all supported desktop targets. Android must be requested explicitly with --target
+    #[clap(long, conflicts_with = "targets")]
+    pub all_targets: bool,
+    /// A format of the manifest written next to the package. It lists the versions, the target,
+    /// and the size and SHA-256 checksum of the package and every packaged file.
+    /// The checksum of the package is also added to the SHA256SUMS file in the same directory
+    #[clap(long, default_value = "json", arg_enum, ignore_case = true)]
+    pub manifest_format: ManifestFormat,
+    /// A format of the software bill of materials included in the package.
+    /// It lists the image, the GlamorousToolkit App, the seed image and the loaded gtoolkit and releaser versions
+    #[clap(long, default_value = "cyclonedx", arg_enum, ignore_case = true)]
+    pub sbom_format: SbomFormat,
+    /// Query the image for the loaded repositories and their commits and add them to the software bill of materials
+    #[clap(long)]
+    pub sbom_repositories: bool,
+    /// Create byte-for-byte reproducible packages: entries are sorted, their modification time is taken
+    /// from the SOURCE_DATE_EPOCH environment variable (1980-01-01 by default), and permissions and
+    /// compression settings are fixed. Android packages are not affected
+    #[clap(long)]
+    pub reproducible: bool,
+    /// A minisign secret key to sign the packages with, the signatures are written next to them as <package>.sig.
+    /// Defaults to the key in the FEENK_SIGNING_KEY environment variable, packages are not signed if there is none.
+    /// Encrypted keys are decrypted with the password from the FEENK_SIGNING_KEY_PASSWORD environment variable
+    #[clap(long, parse(from_os_str))]
+    pub signing_key: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -34,13 +75,6 @@ pub struct ReleaserOptions {
     pub bump: VersionBump,
 }
 
-#[derive(Serialize)]
-struct ReleaseInfo {
-    version: String,
-    os: String,
-    arch: String,
-}
-
 pub struct Release;
 
 impl Release {
@@ -48,84 +82,288 @@ impl Release {
         Self {}
     }
 
-    fn process_template_path(
+    /// Creates release packages for all requested targets, downloading the missing VMs concurrently.
+    /// Returns the paths of the created packages
+    pub async fn package(
+        &self,
         application: &Application,
-        path: impl AsRef<Path>,
-        target: PlatformOS,
-    ) -> PathBuf {
-        let new_version = application.image_version();
-
-        let platform = match target {
-            PlatformOS::MacOSX8664 | PlatformOS::MacOSAarch64 => "MacOS",
-            PlatformOS::WindowsX8664 | PlatformOS::WindowsAarch64 => "Windows",
-            PlatformOS::LinuxX8664 | PlatformOS::LinuxAarch64 => "Linux",
-            PlatformOS::AndroidAarch64 => "Android",
+        release_options: &ReleaseOptions,
+    ) -> Result<Vec<PathBuf>> {
+        // resolve the actual targets
+        let targets = if release_options.all_targets {
+            PlatformOS::value_variants()
+                .iter()
+                .filter(|target| !target.is_android())
+                .copied()
+                .collect()
+        } else if release_options.targets.is_empty() {
+            vec![application.host_platform()]
+        } else {
+            let mut targets = release_options.targets.clone();
+            targets.sort();
+            targets.dedup();
+            targets
         };
 
-        let arch = match target {
-            PlatformOS::MacOSX8664 => "x86_64",
-            PlatformOS::MacOSAarch64 => "aarch64",
-            PlatformOS::WindowsX8664 => "x86_64",
-            PlatformOS::WindowsAarch64 => "aarch64",
-            PlatformOS::LinuxX8664 => "x86_64",
-            PlatformOS::LinuxAarch64 => "aarch64",
-            PlatformOS::AndroidAarch64 => "aarch64",
-        };
+        let customer_level = Downloader::resolve_customer_level(application.customer_level())?;
+        let signer = Signer::from_file_or_env(release_options.signing_key.as_deref())?;
+
+        let packages = Self::package_paths(
+            application,
+            targets,
+            customer_level,
+            &release_options.release,
+        )?;
+
+        // check if the vm for each target exists, and download the missing ones
+        let downloader = Downloader::new().be_silent();
+        try_join_all(
+            packages
+                .iter()
+                .map(|(target, _)| *target)
+                .filter(|target| !application.gtoolkit_app_cli_for_target(*target).exists())
+                .map(|target| {
+                    downloader.download_glamorous_toolkit_vm(application, target, customer_level)
+                }),
+        )
+        .await?;
 
-        let info = ReleaseInfo {
-            version: new_version.to_string(),
-            os: platform.to_string(),
-            arch: arch.to_string(),
+        let repositories = if release_options.sbom_repositories {
+            application.gtoolkit().list_loaded_repositories()?
+        } else {
+            vec![]
         };
 
-        path.as_ref()
+        let packages = packages
+            .into_iter()
+            .map(|(target, package)| {
+                self.package_for_target(
+                    application,
+                    target,
+                    customer_level,
+                    package,
+                    &repositories,
+                    release_options,
+                )
+            })
+            .collect::<Result<Vec<PathBuf>>>()?;
+
+        if let Some(signer) = &signer {
+            for package in &packages {
+                signer.sign(package)?;
+            }
+        }
+
+        Self::write_release_notes(application, &packages)?;
+
+        Ok(packages)
+    }
+
+    /// Renders the path of the package for each target, making sure that the targets are not packaged
+    /// into the same file and that Android packages have the .apk extension
+    fn package_paths(
+        application: &Application,
+        targets: Vec<PlatformOS>,
+        customer_level: CustomerLevel,
+        release: &Path,
+    ) -> Result<Vec<(PlatformOS, PathBuf)>> {
+        let mut packages: Vec<(PlatformOS, PathBuf)> = vec![];
+        for target in targets {
+            let package = PathTemplate::new(release).render(&TemplateVariables::new(
+                application,
+                target,
+                customer_level,
+            ))?;
+            if let Some((other_target, _)) = packages.iter().find(|(_, each)| each == &package) {
+                return InstallerError::ReleasePackagePathConflict(package, *other_target, target)
+                    .into();
+            }
+            if target.is_android()
+                && package.extension().and_then(|extension| extension.to_str()) != Some("apk")
+            {
+                return InstallerError::AndroidPackageExtension(package).into();
+            }
+            packages.push((target, package));
+        }
+        Ok(packages)
+    }
+
+    /// Writes the release notes recorded by `setup --target release` next to the packages.
+    /// Release notes of a different version are ignored
+    fn write_release_notes(application: &Application, packages: &[PathBuf]) -> Result<()> {
+        let release_notes_file = application.workspace().join(RELEASE_NOTES_FILE);
+        if !release_notes_file.exists() {
+            return Ok(());
+        }
+
+        let release_notes = ReleaseNotes::read_from_file(&release_notes_file)?;
+        if release_notes.version != application.image_version().to_string() {
+            return Ok(());
+        }
+
+        let mut directories = packages
             .iter()
-            .map(|each| {
-                let template = mustache::compile_str(each.to_str().unwrap()).unwrap();
-                template.render_to_string(&info).unwrap()
+            .map(|package| {
+                package
+                    .parent()
+                    .filter(|directory| !directory.as_os_str().is_empty())
+                    .map(|directory| directory.to_path_buf())
+                    .unwrap_or_else(|| PathBuf::from("."))
             })
-            .collect::<PathBuf>()
+            .collect::<Vec<PathBuf>>();
+        directories.sort();
+        directories.dedup();
+
+        for directory in directories {
+            release_notes.write_into(directory)?;
+        }
+        Ok(())
     }
 
     /// Creates a release package including vm and an image with all extra resources
     /// Platform specific:
-    ///  - produces a .zip for desktop targets
-    ///  - produces an unsigned .apk
-    pub async fn package(
+    ///  - produces a .zip, .tar.gz or .tar.zst for desktop targets
+    ///  - produces a .deb for Linux
+    ///  - produces an unsigned, zip-aligned .apk for Android
+    fn package_for_target(
         &self,
         application: &Application,
+        target: PlatformOS,
+        customer_level: CustomerLevel,
+        package: PathBuf,
+        repositories: &[LoadedRepository],
         release_options: &ReleaseOptions,
     ) -> Result<PathBuf> {
-        // resolve an actual target
-        let target = release_options
-            .target
-            .unwrap_or_else(|| application.host_platform());
-        // check if the vm for the target exists, and download it otherwise
-        if !application.gtoolkit_app_cli_for_target(target).exists() {
-            Downloader::new()
-                .be_silent()
-                .download_glamorous_toolkit_vm(application, target, CustomerLevel::Auto)
-                .await?;
-        }
+        let sbom_format = release_options.sbom_format;
+        let reproducible = if release_options.reproducible {
+            Some(Reproducible::from_env()?)
+        } else {
+            None
+        };
 
-        if target.is_android() {
-            return self.create_apk(application, target);
+        // the software bill of materials is specific to the target, so it is written
+        // into a temporary folder rather than the workspace and packaged from there
+        let mut sbom = Sbom::new(application, target, customer_level, repositories);
+        if let Some(reproducible) = &reproducible {
+            sbom = sbom.created_at(reproducible.modification_time());
         }
+        let sbom_directory = tempfile::TempDir::new()?;
+        sbom.write_into(sbom_directory.path(), sbom_format)?;
 
-        let package =
-            Self::process_template_path(application, release_options.release.as_path(), target);
+        let format = ArchiveFormat::from_path(&package);
 
-        ToZip::new(package)
-            .one_entry(FileNamed::wildmatch("*.image").within(application.workspace()))
-            .one_entry(FileNamed::wildmatch("*.changes").within(application.workspace()))
-            .one_entry(FileNamed::wildmatch("*.sources").within(application.workspace()))
-            .folder(application.workspace().join("gt-extra"))
-            .one_entries(Package::gtoolkit_app_entries_for_target(
+        let package = if target.is_android() {
+            self.create_apk(
                 application,
                 target,
-            ))
-            .zip()
-            .map_err(|error| error.into())
+                package,
+                sbom_directory.path(),
+                sbom_format,
+            )?
+        } else {
+            let mut entries =
+                Self::workspace_entries(application, sbom_directory.path(), sbom_format);
+            entries.extend(Package::gtoolkit_app_entries_for_target(
+                application,
+                target,
+            ));
+
+            match (format, reproducible) {
+                (ArchiveFormat::Zip, Some(reproducible)) => {
+                    ToReproducibleZip::new(package, reproducible)
+                        .one_entries(entries)
+                        .zip()?
+                }
+                (ArchiveFormat::Zip, None) => ToZip::new(package).one_entries(entries).zip()?,
+                (ArchiveFormat::Tar(compression), _) => ToTar::new(package, compression)
+                    .reproducible(reproducible)
+                    .one_entries(entries)
+                    .tar()?,
+                (ArchiveFormat::Deb, _) => {
+                    let mut paths = vec![];
+                    for entry in entries {
+                        paths.push(entry.find()?);
+                    }
+                    ToDebian::new(
+                        package,
+                        target,
+                        application.image_version().to_string(),
+                        application.app_version().to_string(),
+                    )
+                    .reproducible(reproducible)
+                    .entries(paths)
+                    .deb()?
+                }
+            }
+        };
+
+        let manifest = ReleaseManifest::new(
+            application,
+            target,
+            customer_level,
+            &package,
+            Self::packaged_files(
+                application,
+                target,
+                &package,
+                format,
+                sbom_directory.path(),
+                sbom_format,
+            )?,
+        )?;
+        manifest.write(&package, release_options.manifest_format)?;
+        Checksums::for_package(&package).add(&manifest)?;
+
+        sbom_directory.close()?;
+        Ok(package)
+    }
+
+    /// The image and its resources from the workspace that are part of every release package,
+    /// together with the software bill of materials from a given folder
+    fn workspace_entries(
+        application: &Application,
+        sbom_directory: &Path,
+        sbom_format: SbomFormat,
+    ) -> Vec<OneEntry> {
+        vec![
+            FileNamed::wildmatch("*.image").within(application.workspace()),
+            FileNamed::wildmatch("*.changes").within(application.workspace()),
+            FileNamed::wildmatch("*.sources").within(application.workspace()),
+            FolderNamed::exact("gt-extra").within(application.workspace()),
+            FileNamed::exact(sbom_format.file_name()).within(sbom_directory),
+        ]
+    }
+
+    /// Lists the files of the release package with their paths relative to the root of the package.
+    /// Zip-based packages are read once built: the libraries are stripped while building an .apk
+    /// and zip archives store the content of symlinks rather than the links themselves.
+    /// A Debian package installs everything into its installation directory
+    fn packaged_files(
+        application: &Application,
+        target: PlatformOS,
+        package: &Path,
+        format: ArchiveFormat,
+        sbom_directory: &Path,
+        sbom_format: SbomFormat,
+    ) -> Result<Vec<ManifestFile>> {
+        if target.is_android() || format == ArchiveFormat::Zip {
+            return ManifestFile::collect_archive(package);
+        }
+
+        let app_directory = if format == ArchiveFormat::Deb {
+            DEBIAN_INSTALLATION_DIRECTORY
+        } else {
+            ""
+        };
+
+        let mut files = vec![];
+        for entry in Self::workspace_entries(application, sbom_directory, sbom_format) {
+            files.extend(ManifestFile::collect(entry.find()?, app_directory)?);
+        }
+        for entry in Package::gtoolkit_app_entries_for_target(application, target) {
+            files.extend(ManifestFile::collect(entry.find()?, app_directory)?);
+        }
+        Ok(files)
     }
 
     pub async fn run_releaser(
@@ -149,65 +387,187 @@ impl Release {
         Ok(())
     }
 
-    fn create_apk(&self, application: &Application, target: PlatformOS) -> Result<PathBuf> {
+    /// Assembles an unsigned, zip-aligned .apk out of the downloaded VM libraries and its AndroidManifest.xml.
+    /// The image, changes, sources and gt-extra are packaged as assets.
+    /// Requires the Android SDK and NDK, see `ndk_build::ndk::Ndk::from_env`
+    fn create_apk(
+        &self,
+        application: &Application,
+        target: PlatformOS,
+        package: PathBuf,
+        sbom_directory: &Path,
+        sbom_format: SbomFormat,
+    ) -> Result<PathBuf> {
         use ndk_build::apk::{ApkConfig, StripConfig};
-        use ndk_build::manifest::{
-            Activity as AndroidActivity, AndroidManifest, Application as AndroidApplication,
-            IntentFilter as AndroidIntentFilter, MetaData as AndroidMetaData,
-            Permission as AndroidPermission,
-        };
+        use ndk_build::manifest::AndroidManifest;
         use ndk_build::ndk::Ndk;
-
         use ndk_build::target::Target as AndroidTarget;
 
         let android_target = match target {
             PlatformOS::AndroidAarch64 => AndroidTarget::Arm64V8a,
-            _ => {
-                panic!("Unsupported android target: {:?}", target)
-            }
+            _ => return InstallerError::UnsupportedAndroidTarget(target).into(),
+        };
+
+        let app_location = application.gtoolkit_app_location(target);
+
+        let manifest_path = app_location.join("AndroidManifest.xml");
+        if !manifest_path.exists() {
+            return InstallerError::AndroidManifestNotFound(manifest_path).into();
+        }
+        let manifest_file = File::open(manifest_path.as_path())?;
+        let manifest: AndroidManifest = serde_xml_rs::from_reader(BufReader::new(manifest_file))
+            .map_err(|error| InstallerError::AndroidManifestParseError(manifest_path, error))?;
+
+        let libraries_directory = app_location.join("lib").join(android_target.android_abi());
+        let libraries = FilesNamed::wildmatch("*.so")
+            .within(&libraries_directory)
+            .find()?;
+        if libraries.is_empty() {
+            return InstallerError::AndroidLibrariesNotFound(libraries_directory).into();
+        }
+
+        // the assets are staged outside of the App, so that its own files are never touched
+        let assets = tempfile::TempDir::new()?;
+        for entry in Self::workspace_entries(application, sbom_directory, sbom_format) {
+            entry.copy(assets.path())?;
+        }
+
+        let build_dir = package
+            .parent()
+            .filter(|directory| !directory.as_os_str().is_empty())
+            .map(|directory| directory.to_path_buf())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let apk_name = package
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| InstallerError::FailedToReadFileName(package.clone()))?
+            .to_string();
+
+        let ndk = Ndk::from_env()?;
+        let config = ApkConfig {
+            ndk,
+            build_dir,
+            apk_name,
+            assets: Some(assets.path().to_path_buf()),
+            resources: None,
+            manifest,
+            disable_aapt_compression: false,
+            strip: StripConfig::Default,
+            reverse_port_forward: Default::default(),
         };
 
-        let manifest_path = application
-            .gtoolkit_app_location(target)
-            .join("AndroidManifest.xml");
-
-        let manifest_file = File::open(manifest_path.as_path()).unwrap();
-        let manifest: AndroidManifest =
-            serde_xml_rs::from_reader(BufReader::new(manifest_file)).unwrap();
-
-        println!("manifest: {:#?}", &manifest);
-
-        Ok(manifest_path)
-
-        // let ndk = Ndk::from_env().unwrap();
-        // let config = ApkConfig {
-        //     ndk: ndk.clone(),
-        //     build_dir: bundle_location.clone(),
-        //     apk_name: app_name.to_string(),
-        //     assets: None,
-        //     resources: None,
-        //     manifest,
-        //     disable_aapt_compression: false,
-        //     strip: StripConfig::Default,
-        //     reverse_port_forward: Default::default(),
-        // };
-        //
-        // let mut apk = config.create_apk().expect("Create APK");
-        // let lib_search_path = self.compiled_libraries_directory(options);
-        //
-        // self.compiled_libraries(options)
-        //     .iter()
-        //     .for_each(|compiled_library_path| {
-        //         apk.add_lib_recursively(
-        //             &compiled_library_path,
-        //             android_target,
-        //             &[lib_search_path.as_path()],
-        //         )
-        //         .expect("Add runtime lib")
-        //     });
-        //
-        // let aligned_apk = apk
-        //     .add_pending_libs_and_align()
-        //     .expect("Add pending libs and align");
+        let mut apk = config.create_apk()?;
+        for library in &libraries {
+            apk.add_lib_recursively(library, android_target, &[libraries_directory.as_path()])?;
+        }
+        apk.add_pending_libs_and_align()?;
+        assets.close()?;
+
+        let aligned_apk = config.apk();
+        if !aligned_apk.exists() {
+            return InstallerError::AndroidPackageNotCreated(aligned_apk).into();
+        }
+        if aligned_apk != package {
+            std::fs::rename(&aligned_apk, &package)?;
+        }
+
+        Ok(package)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANDROID: PlatformOS = PlatformOS::AndroidAarch64;
+
+    fn create_apk(application: &Application) -> Result<PathBuf> {
+        let sbom_directory = tempfile::tempdir().unwrap();
+        Release::new().create_apk(
+            application,
+            ANDROID,
+            application.workspace().join("GlamorousToolkit.apk"),
+            sbom_directory.path(),
+            SbomFormat::CycloneDx,
+        )
+    }
+
+    fn write_android_manifest(application: &Application) {
+        let app_location = application.gtoolkit_app_location(ANDROID);
+        std::fs::create_dir_all(&app_location).unwrap();
+        std::fs::write(
+            app_location.join("AndroidManifest.xml"),
+            r#"<manifest package="com.gtoolkit"></manifest>"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn android_package_requires_the_apk_extension() {
+        let directory = tempfile::tempdir().unwrap();
+        let application = Application::for_tests(directory.path());
+
+        let error = Release::package_paths(
+            &application,
+            vec![ANDROID],
+            CustomerLevel::Regular,
+            Path::new("GlamorousToolkit-{{os}}.zip"),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            InstallerError::AndroidPackageExtension(package) if package == Path::new("GlamorousToolkit-Android.zip")
+        ));
+
+        let packages = Release::package_paths(
+            &application,
+            vec![ANDROID],
+            CustomerLevel::Regular,
+            Path::new("GlamorousToolkit-{{os}}.apk"),
+        )
+        .unwrap();
+        assert_eq!(
+            packages,
+            vec![(ANDROID, PathBuf::from("GlamorousToolkit-Android.apk"))]
+        );
+    }
+
+    #[test]
+    fn create_apk_requires_the_android_manifest() {
+        let directory = tempfile::tempdir().unwrap();
+        let application = Application::for_tests(directory.path());
+
+        assert!(matches!(
+            create_apk(&application),
+            Err(InstallerError::AndroidManifestNotFound(manifest))
+                if manifest == application.gtoolkit_app_location(ANDROID).join("AndroidManifest.xml")
+        ));
+        assert!(!application
+            .workspace()
+            .join("GlamorousToolkit.apk")
+            .exists());
+    }
+
+    #[test]
+    fn create_apk_requires_the_libraries() {
+        let directory = tempfile::tempdir().unwrap();
+        let application = Application::for_tests(directory.path());
+        write_android_manifest(&application);
+
+        let libraries_directory = application
+            .gtoolkit_app_location(ANDROID)
+            .join("lib")
+            .join("arm64-v8a");
+        std::fs::create_dir_all(&libraries_directory).unwrap();
+        std::fs::write(libraries_directory.join("libGlamorous.txt"), b"").unwrap();
+
+        assert!(matches!(
+            create_apk(&application),
+            Err(InstallerError::AndroidLibrariesNotFound(libraries)) if libraries == libraries_directory
+        ));
+        assert!(!application
+            .workspace()
+            .join("GlamorousToolkit.apk")
+            .exists());
     }
 }
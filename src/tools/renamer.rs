@@ -1,5 +1,8 @@
-use crate::{Application, ExecutableSmalltalk, ImageSeed, Result, SmalltalkCommand};
-use clap::Parser;
+use crate::{
+    Application, ExecutableSmalltalk, ImageSeed, InstallerError, Result, SmalltalkCommand,
+};
+use clap::{ArgEnum, Parser};
+use std::path::Path;
 
 pub struct Renamer;
 
@@ -7,6 +10,27 @@ pub struct Renamer;
 pub struct RenameOptions {
     /// A new name of the image without the extension
     pub name: String,
+    /// How to rename the image: 'offline' renames the .image and .changes files keeping the change history,
+    /// 'vm' launches the GlamorousToolkit App to save the image under the new name and deletes the old .changes
+    #[clap(long, default_value = "offline", arg_enum, ignore_case = true)]
+    pub mode: RenameMode,
+}
+
+impl RenameOptions {
+    /// The new name must be a plain file name within the workspace
+    pub fn validate(&self) -> Result<()> {
+        let name = self.name.as_str();
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return InstallerError::InvalidImageName(name.to_string()).into();
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+pub enum RenameMode {
+    Offline,
+    Vm,
 }
 
 impl Renamer {
@@ -19,19 +43,38 @@ impl Renamer {
         application: &mut Application,
         rename_options: &RenameOptions,
     ) -> Result<()> {
+        rename_options.validate()?;
+
         let current_image_path = application.image();
         let current_changes_file = current_image_path.with_extension("changes");
 
         let new_image_path =
             current_image_path.with_file_name(format!("{}.image", rename_options.name.as_str()));
+        let new_changes_file = new_image_path.with_extension("changes");
 
-        SmalltalkCommand::new("save")
-            .arg(rename_options.name.as_str())
-            .arg("--delete-old")
-            .execute(application.gtoolkit().evaluator().save(true))?;
+        for file in [&new_image_path, &new_changes_file] {
+            if file.exists() {
+                return InstallerError::ImageAlreadyExists(file.clone()).into();
+            }
+        }
 
-        if current_changes_file.exists() {
-            std::fs::remove_file(current_changes_file)?;
+        match rename_options.mode {
+            RenameMode::Offline => Self::rename_files(
+                &current_image_path,
+                &new_image_path,
+                &current_changes_file,
+                &new_changes_file,
+            )?,
+            RenameMode::Vm => {
+                SmalltalkCommand::new("save")
+                    .arg(rename_options.name.as_str())
+                    .arg("--delete-old")
+                    .execute(application.gtoolkit().evaluator().save(true))?;
+
+                if current_changes_file.exists() {
+                    std::fs::remove_file(current_changes_file)?;
+                }
+            }
         }
 
         application.set_image_seed(ImageSeed::Image(new_image_path))?;
@@ -39,4 +82,126 @@ impl Renamer {
 
         Ok(())
     }
+
+    /// Renames the image and its changes on disk, renaming the image back if the changes can not be renamed
+    fn rename_files(
+        current_image_path: &Path,
+        new_image_path: &Path,
+        current_changes_file: &Path,
+        new_changes_file: &Path,
+    ) -> Result<()> {
+        if !current_image_path.exists() {
+            return InstallerError::ImageNotFound(current_image_path.to_path_buf()).into();
+        }
+
+        std::fs::rename(current_image_path, new_image_path)?;
+
+        if current_changes_file.exists() {
+            if let Err(error) = std::fs::rename(current_changes_file, new_changes_file) {
+                std::fs::rename(new_image_path, current_image_path)?;
+                return Err(error.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_workspace(workspace: &Path) -> Application {
+        let application = Application::for_tests(workspace);
+        std::fs::write(application.image(), "image").unwrap();
+        std::fs::write(application.image().with_extension("changes"), "changes").unwrap();
+        application.serialize_into_file().unwrap();
+        application
+    }
+
+    fn options(name: &str) -> RenameOptions {
+        RenameOptions::parse_from(["rename", name])
+    }
+
+    #[tokio::test]
+    async fn renames_the_image_and_changes() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut application = create_workspace(directory.path());
+        let old_image = application.image();
+
+        Renamer::new()
+            .rename(&mut application, &options("Renamed"))
+            .await
+            .unwrap();
+
+        assert!(!old_image.exists());
+        assert!(!old_image.with_extension("changes").exists());
+        assert_eq!(
+            std::fs::read_to_string(directory.path().join("Renamed.image")).unwrap(),
+            "image"
+        );
+        assert_eq!(
+            std::fs::read_to_string(directory.path().join("Renamed.changes")).unwrap(),
+            "changes"
+        );
+
+        let serialized = Application::for_workspace_from_file(directory.path()).unwrap();
+        assert_eq!(serialized.image_name(), "Renamed");
+        assert!(serialized.image().exists());
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_names() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut application = create_workspace(directory.path());
+
+        for name in ["", ".image", "images/Renamed", "images\\Renamed"] {
+            assert!(matches!(
+                Renamer::new()
+                    .rename(&mut application, &options(name))
+                    .await,
+                Err(InstallerError::InvalidImageName(invalid)) if invalid == name
+            ));
+        }
+        assert!(options("Renamed-v1.0").validate().is_ok());
+        assert!(application.image().exists());
+    }
+
+    #[tokio::test]
+    async fn does_not_replace_an_existing_image() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut application = create_workspace(directory.path());
+        std::fs::write(directory.path().join("Renamed.changes"), "other changes").unwrap();
+
+        assert!(matches!(
+            Renamer::new()
+                .rename(&mut application, &options("Renamed"))
+                .await,
+            Err(InstallerError::ImageAlreadyExists(_))
+        ));
+        assert!(application.image().exists());
+        assert!(!directory.path().join("Renamed.image").exists());
+    }
+
+    #[test]
+    fn restores_the_image_when_the_changes_can_not_be_renamed() {
+        let directory = tempfile::tempdir().unwrap();
+        let application = create_workspace(directory.path());
+        let image = application.image();
+        let changes = image.with_extension("changes");
+        let new_image = directory.path().join("Renamed.image");
+
+        // the changes can not be renamed into a folder that does not exist
+        assert!(Renamer::rename_files(
+            &image,
+            &new_image,
+            &changes,
+            &directory.path().join("missing/Renamed.changes"),
+        )
+        .is_err());
+
+        assert_eq!(std::fs::read_to_string(&image).unwrap(), "image");
+        assert_eq!(std::fs::read_to_string(&changes).unwrap(), "changes");
+        assert!(!new_image.exists());
+    }
 }
@@ -1,5 +1,8 @@
 use crate::gtoolkit::GToolkit;
-use crate::{Application, Result, StartOptions, Starter, BUILDING, CREATING};
+use crate::{
+    Application, ReleaseNotes, Result, StartOptions, Starter, BUILDING, CREATING,
+    RELEASE_NOTES_FILE,
+};
 use clap::{ArgEnum, Parser};
 use feenk_releaser::VersionBump;
 use std::str::FromStr;
@@ -88,7 +91,10 @@ impl Setup {
                     .perform_setup_for_release(setup_options.bump.clone())?;
                 let gtoolkit_version = application.gtoolkit().get_gtoolkit_version()?;
                 application.set_image_version(gtoolkit_version.into());
-                application.gtoolkit().print_new_commits()?;
+                let new_commits = application.gtoolkit().get_new_commits()?;
+                println!("{}", &new_commits);
+                ReleaseNotes::from_new_commits(application.image_version(), &new_commits)
+                    .write_json(application.workspace().join(RELEASE_NOTES_FILE))?;
             }
         }
 
@@ -0,0 +1,189 @@
+use clap::Parser;
+use indicatif::HumanBytes;
+
+use crate::{Application, Result, Snapshots, SPARKLE};
+
+#[derive(Parser, Debug, Clone)]
+pub struct SnapshotOptions {
+    #[clap(subcommand)]
+    pub command: SnapshotCommand,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub enum SnapshotCommand {
+    /// Copies the image, changes and gtoolkit.yaml into a new snapshot in the `snapshots` folder of the workspace
+    Create(CreateSnapshotOptions),
+    /// Prints the snapshots, the oldest first
+    List,
+    /// Replaces the image, changes and gtoolkit.yaml of the workspace with the ones from a snapshot
+    Restore(RestoreSnapshotOptions),
+    /// Deletes snapshots
+    Delete(DeleteSnapshotOptions),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CreateSnapshotOptions {
+    /// A name of the snapshot. Defaults to the current date and time
+    pub name: Option<String>,
+    /// Compress the files of the snapshot with zstd
+    #[clap(long)]
+    pub compress: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct RestoreSnapshotOptions {
+    /// A name of the snapshot to restore
+    pub name: String,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct DeleteSnapshotOptions {
+    /// Names of the snapshots to delete
+    #[clap(required = true)]
+    pub names: Vec<String>,
+}
+
+pub struct Snapshotter;
+
+impl Snapshotter {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn snapshot(
+        &self,
+        application: &mut Application,
+        snapshot_options: &SnapshotOptions,
+    ) -> Result<()> {
+        let snapshots = Snapshots::for_application(application);
+
+        match &snapshot_options.command {
+            SnapshotCommand::Create(create_options) => {
+                let snapshot = snapshots.create(
+                    application,
+                    create_options.name.as_deref(),
+                    create_options.compress,
+                )?;
+                println!("{}Created snapshot {}", SPARKLE, &snapshot.name);
+            }
+            SnapshotCommand::List => {
+                for snapshot in snapshots.list()? {
+                    println!(
+                        "{:<32} {} {:<10} {:>12} {}",
+                        &snapshot.name,
+                        snapshot.created_at.format("%Y-%m-%d %H:%M:%S"),
+                        &snapshot.image_version,
+                        HumanBytes(snapshots.size_of(&snapshot)?).to_string(),
+                        snapshot
+                            .automatic
+                            .as_ref()
+                            .map(|command| format!("(before {})", command))
+                            .unwrap_or_default()
+                    );
+                }
+            }
+            SnapshotCommand::Restore(restore_options) => {
+                let snapshot = snapshots.restore(application, &restore_options.name)?;
+                *application = Application::for_workspace_from_file(application.workspace())?;
+                println!(
+                    "{}Restored snapshot {} of v{}",
+                    SPARKLE, &snapshot.name, &snapshot.image_version
+                );
+            }
+            SnapshotCommand::Delete(delete_options) => {
+                for name in &delete_options.names {
+                    snapshots.delete(name)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates a snapshot of the workspace before a command that modifies the image
+    pub fn snapshot_before(&self, application: &Application, command: &str) -> Result<()> {
+        if !application.image().exists() {
+            return Ok(());
+        }
+
+        let snapshot =
+            Snapshots::for_application(application).create_automatic(application, command)?;
+        println!("Created snapshot {} before {}", &snapshot.name, command);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_before_requires_an_image() {
+        let directory = tempfile::tempdir().unwrap();
+        let application = Application::for_tests(directory.path());
+
+        Snapshotter::new()
+            .snapshot_before(&application, "test")
+            .unwrap();
+        assert!(Snapshots::for_application(&application)
+            .list()
+            .unwrap()
+            .is_empty());
+
+        std::fs::write(application.image(), b"image").unwrap();
+        Snapshotter::new()
+            .snapshot_before(&application, "test")
+            .unwrap();
+        let snapshots = Snapshots::for_application(&application).list().unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].automatic.as_deref(), Some("test"));
+        assert_eq!(snapshots[0].files, vec!["GlamorousToolkit.image"]);
+    }
+
+    #[test]
+    fn restore_reloads_the_application() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut application = Application::for_tests(directory.path());
+        std::fs::write(application.image(), b"image").unwrap();
+        application.serialize_into_file().unwrap();
+
+        let command = |command| SnapshotOptions { command };
+        Snapshotter::new()
+            .snapshot(
+                &mut application,
+                &command(SnapshotCommand::Create(CreateSnapshotOptions {
+                    name: Some("before".to_string()),
+                    compress: false,
+                })),
+            )
+            .unwrap();
+
+        application.set_verbose(true);
+        application.serialize_into_file().unwrap();
+        std::fs::write(application.image(), b"modified image").unwrap();
+
+        Snapshotter::new()
+            .snapshot(
+                &mut application,
+                &command(SnapshotCommand::Restore(RestoreSnapshotOptions {
+                    name: "before".to_string(),
+                })),
+            )
+            .unwrap();
+        assert!(!application.is_verbose());
+        assert_eq!(std::fs::read(application.image()).unwrap(), b"image");
+
+        Snapshotter::new()
+            .snapshot(
+                &mut application,
+                &command(SnapshotCommand::Delete(DeleteSnapshotOptions {
+                    names: vec!["before".to_string()],
+                })),
+            )
+            .unwrap();
+        assert!(Snapshots::for_application(&application)
+            .list()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -5,17 +5,34 @@ use file_matcher::FileNamed;
 use unzipper::{FileToUnzip, FilesToUnzip};
 use zipper::ToZip;
 
-use crate::{Application, CustomerLevel, Downloader, Package, Result};
+use crate::{
+    Application, CustomerLevel, Downloader, Package, PathTemplate, Reproducible, Result,
+    TemplateVariables, TentativeManifest, ToReproducibleZip, TENTATIVE_MANIFEST_FILE,
+};
 
 #[derive(Parser, Debug, Clone)]
 pub struct TentativeOptions {
-    /// Path to the .zip with the tentative image build
+    /// Path to the .zip with the tentative image build.
+    /// When packaging, supports the same mustache variables as `package-release`, for example "GlamorousToolkit-{{image_name}}-{{build_id}}.zip"
     #[clap(parse(from_os_str))]
     pub tentative: PathBuf,
-    /// When packaging or un-packaging, do not fail when some of the items do not exist.
+    /// When packaging, do not fail when gt-extra or the Docker files do not exist.
     /// This may be useful when packaging a local build.
+    /// When un-packaging, the package is always verified against its manifest
     #[clap(long)]
     pub ignore_absent: bool,
+    /// When un-packaging, specify which customer-level GToolkit VM to download: 'auto', 'regular' or 'pro'.
+    /// Defaults to the customer level the tentative image was built with
+    #[clap(long, arg_enum, ignore_case = true)]
+    pub customer_level: Option<CustomerLevel>,
+    /// When un-packaging, do not download the GToolkit VM
+    #[clap(long)]
+    pub skip_vm_download: bool,
+    /// When packaging, create a byte-for-byte reproducible package: entries are sorted, their modification time
+    /// is taken from the SOURCE_DATE_EPOCH environment variable (1980-01-01 by default),
+    /// and permissions and compression settings are fixed
+    #[clap(long)]
+    pub reproducible: bool,
 }
 
 pub struct Tentative;
@@ -30,33 +47,73 @@ impl Tentative {
         application: &Application,
         tentative_options: &TentativeOptions,
     ) -> Result<PathBuf> {
-        let mut zip = ToZip::new(tentative_options.tentative.as_path())
-            .one_entry(FileNamed::wildmatch("*.image").within(application.workspace()))
-            .one_entry(FileNamed::wildmatch("*.changes").within(application.workspace()))
-            .one_entry(FileNamed::wildmatch("*.sources").within(application.workspace()))
-            .one_entry(
-                FileNamed::exact(Application::serialization_file_name())
-                    .within(application.workspace()),
-            )
-            .one_entries(Package::gtoolkit_app_folders(application));
+        let tentative = PathTemplate::new(tentative_options.tentative.as_path()).render(
+            &TemplateVariables::new(
+                application,
+                application.host_platform(),
+                Downloader::resolve_customer_level(application.customer_level())?,
+            ),
+        )?;
+
+        let mut entries = vec![
+            FileNamed::wildmatch("*.image").within(application.workspace()),
+            FileNamed::wildmatch("*.changes").within(application.workspace()),
+            FileNamed::wildmatch("*.sources").within(application.workspace()),
+            FileNamed::exact(Application::serialization_file_name())
+                .within(application.workspace()),
+        ];
+        entries.extend(Package::gtoolkit_app_folders(application));
+
+        let mut packaged = vec![];
+        for entry in entries {
+            packaged.push(entry.find()?);
+        }
 
         let gt_extra = application.workspace().join("gt-extra");
 
         if gt_extra.exists() || !tentative_options.ignore_absent {
-            zip.add_folder(gt_extra);
+            packaged.push(gt_extra);
         }
 
         // Add Docker files
         let pwd = application.workspace().parent();
         match pwd {
             None => {}
-            Some(path) => {
-                zip.add_file(path.join(Application::dockerfile()));
-                zip.add_folder(path.join(Application::docker_image_content_directory()))
-            }
+            Some(path) => packaged.extend(
+                [
+                    path.join(Application::dockerfile()),
+                    path.join(Application::docker_image_content_directory()),
+                ]
+                .into_iter()
+                .filter(|docker_path| docker_path.exists() || !tentative_options.ignore_absent),
+            ),
         }
 
-        zip.zip().map_err(|error| error.into())
+        // the manifest allows unpackage-tentative to verify the package before using it
+        let manifest = TentativeManifest::new(application, &packaged)?
+            .write(application.workspace().join(TENTATIVE_MANIFEST_FILE))?;
+
+        let zipped = if tentative_options.reproducible {
+            Reproducible::from_env().and_then(|reproducible| {
+                ToReproducibleZip::new(tentative, reproducible)
+                    .path(&manifest)
+                    .paths(packaged)
+                    .zip()
+            })
+        } else {
+            let mut zip = ToZip::new(tentative).file(&manifest);
+            for path in packaged {
+                if path.is_dir() {
+                    zip.add_folder(path);
+                } else {
+                    zip.add_file(path);
+                }
+            }
+            zip.zip().map_err(|error| error.into())
+        };
+
+        std::fs::remove_file(&manifest)?;
+        zipped
     }
 
     pub async fn unpackage(
@@ -64,6 +121,8 @@ impl Tentative {
         application: &mut Application,
         tentative_options: &TentativeOptions,
     ) -> Result<()> {
+        TentativeManifest::verify_archive(tentative_options.tentative.as_path())?;
+
         let files_to_unzip = FilesToUnzip::new().add(FileToUnzip::new(
             tentative_options.tentative.as_path(),
             application.workspace(),
@@ -71,15 +130,32 @@ impl Tentative {
 
         files_to_unzip.unzip().await?;
 
-        let application = Application::for_workspace_from_file(application.workspace())?;
+        // the manifest is only needed to verify the package
+        let manifest = application.workspace().join(TENTATIVE_MANIFEST_FILE);
+        if manifest.exists() {
+            std::fs::remove_file(&manifest)?;
+        }
 
-        Downloader::new()
-            .download_glamorous_toolkit_vm(
-                &application,
-                application.host_platform(),
-                CustomerLevel::Auto,
-            )
-            .await?;
+        let mut unpackaged = Application::for_workspace_from_file(application.workspace())?;
+        unpackaged.set_verbose(application.is_verbose());
+        if let Some(app_cli_binary) = application.app_cli_binary() {
+            unpackaged.set_app_cli_binary(app_cli_binary)?;
+        }
+        if let Some(customer_level) = tentative_options.customer_level {
+            unpackaged.set_customer_level(customer_level);
+        }
+        unpackaged.serialize_into_file()?;
+        *application = unpackaged;
+
+        if !tentative_options.skip_vm_download {
+            Downloader::new()
+                .download_glamorous_toolkit_vm(
+                    application,
+                    application.host_platform(),
+                    application.customer_level(),
+                )
+                .await?;
+        }
 
         Ok(())
     }
@@ -1,23 +1,123 @@
 use crate::gtoolkit::GToolkit;
 use crate::Application;
-use crate::Result;
+use crate::{
+    DurationReport, FileToMove, FolderToMove, InstallerError, JUnitReport, Result, TestHistory,
+    TestRun, TestStage, TimeBudget,
+};
+use chrono::Utc;
 use clap::Parser;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::DirEntry;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+use wildmatch::WildMatch;
+
+/// A report that is exported when testing the release
+pub const DEFAULT_REPORT: &str = "GtGtoolkitArchitecturalReport";
+
+/// Workspace entries that are modified while the image runs and therefore
+/// must not be collected as the output of a report
+const REPORT_IGNORED_ENTRIES: [&str; 5] = [
+    "*.image",
+    "*.changes",
+    "*.log",
+    "pharo-local",
+    crate::SERIALIZATION_FILE,
+];
 
 pub struct Tester;
 
 #[derive(Parser, Debug, Clone)]
 pub struct TestOptions {
     /// Select packages to test. If not specified will run all tests, all slides and architectural reports.
+    /// Supports glob patterns, for example `GToolkit-*`
     #[clap(long, min_values = 1)]
     pub packages: Option<Vec<String>>,
     /// Disable automatic deprecation rewrites during testing phase
     #[clap(long)]
     pub disable_deprecation_rewrites: bool,
-    /// Disable running Pharo's TestCase when packages are provided. Please note that Pharo's `test` runner does not support skipping packages
+    /// Disable running Pharo's TestCase when packages are provided
     #[clap(long)]
     pub disable_tests: bool,
+    /// Select packages to skip, both when running examples and Pharo's TestCase. Supports glob patterns, for example `*-Deprecated`
     #[clap(long, min_values = 1)]
     pub skip_packages: Option<Vec<String>>,
+    /// Print the packages that would be tested, one per line, without running them
+    #[clap(long)]
+    pub list: bool,
+    /// Print the time spent in each stage and the slowest packages and examples based on the exported JUnit XML
+    #[clap(long)]
+    pub duration_report: bool,
+    /// The amount of the slowest packages and examples to include in the duration report
+    #[clap(long, default_value = "10")]
+    pub slowest: usize,
+    /// Path to a .yaml file that maps package names or glob patterns to a time budget, for example `GToolkit-*: 5 minutes`.
+    /// Fails the run when testing a package takes longer than its budget
+    #[clap(long, parse(from_os_str))]
+    pub time_budget: Option<PathBuf>,
+    /// Export a report with a given class name. Can be specified multiple times.
+    /// When no packages are selected defaults to `GtGtoolkitArchitecturalReport`
+    #[clap(long = "report", multiple_occurrences = true)]
+    pub reports: Vec<String>,
+    /// Move the files produced by the reports into a given directory, in a sub-directory per report
+    #[clap(long, parse(from_os_str))]
+    pub report_output: Option<PathBuf>,
+    /// Do not export any report when testing the release
+    #[clap(long, conflicts_with = "reports")]
+    pub no_report: bool,
+    /// Do not record the outcome of examples and tests in the test history
+    #[clap(long)]
+    pub no_history: bool,
+    /// Path to the test history file. Defaults to `test-history.jsonl` in the workspace
+    #[clap(long, parse(from_os_str))]
+    pub history_file: Option<PathBuf>,
+}
+
+impl TestOptions {
+    /// Returns true if the results should be read from the exported JUnit XML
+    fn should_collect_results(&self) -> bool {
+        self.duration_report || self.time_budget.is_some() || !self.no_history
+    }
+
+    /// Returns the class names of the reports to export
+    fn reports_to_export(&self) -> Vec<String> {
+        if self.no_report {
+            vec![]
+        } else if !self.reports.is_empty() {
+            self.reports.clone()
+        } else if self.packages.is_none() {
+            vec![DEFAULT_REPORT.to_string()]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ListPackagesOptions {
+    /// Only list packages matching given glob patterns
+    pub patterns: Vec<String>,
+    /// Only list packages that contain examples
+    #[clap(long, conflicts_with = "tests")]
+    pub examples: bool,
+    /// Only list packages that contain Pharo's TestCase
+    #[clap(long, conflicts_with = "examples")]
+    pub tests: bool,
+}
+
+/// A package in the image that contains examples, TestCases or both
+#[derive(Debug, Clone)]
+pub struct TestablePackage {
+    pub name: String,
+    pub has_examples: bool,
+    pub has_tests: bool,
+}
+
+impl TestablePackage {
+    pub fn is_testable(&self) -> bool {
+        self.has_examples || self.has_tests
+    }
 }
 
 impl Tester {
@@ -28,17 +128,496 @@ impl Tester {
     pub async fn test(&self, application: &Application, test_options: &TestOptions) -> Result<()> {
         let gtoolkit = application.gtoolkit();
 
+        let time_budget = test_options
+            .time_budget
+            .as_ref()
+            .map(TimeBudget::from_file)
+            .transpose()?;
+
+        let testable_packages = if Self::should_resolve_packages(test_options) {
+            gtoolkit.list_testable_packages()?
+        } else {
+            vec![]
+        };
+        let test_options = Self::resolve_test_options(test_options, &testable_packages)?;
+
+        if test_options.list {
+            if let Some(ref packages) = test_options.packages {
+                packages.iter().for_each(|package| println!("{}", package));
+            }
+            return Ok(());
+        }
+
+        let started = Utc::now();
+        let mut durations = DurationReport::new();
+
+        // the history is recorded even if a stage fails, so that its results are not lost
+        let stages = Self::run_stages(
+            application,
+            &test_options,
+            &testable_packages,
+            &mut durations,
+        )
+        .await;
+
+        if !test_options.no_history {
+            let run = TestRun::new(application, started, durations.suites());
+            if !run.results.is_empty() {
+                TestHistory::for_application(application, test_options.history_file.as_deref())
+                    .append(&run)?;
+            }
+        }
+        stages?;
+
+        if test_options.duration_report {
+            durations.print(test_options.slowest);
+        }
+
+        if let Some(time_budget) = time_budget {
+            time_budget.check(&durations)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the examples, tests and reports, adding the duration and results of each stage to the report
+    async fn run_stages(
+        application: &Application,
+        test_options: &TestOptions,
+        testable_packages: &[TestablePackage],
+        durations: &mut DurationReport,
+    ) -> Result<()> {
+        let gtoolkit = application.gtoolkit();
+
         if let Some(ref packages) = test_options.packages {
-            gtoolkit.run_examples(packages, test_options)?;
+            if !packages.is_empty() {
+                Self::run_stage(
+                    application,
+                    test_options,
+                    durations,
+                    TestStage::Examples,
+                    || gtoolkit.run_examples(packages, test_options),
+                )?;
+            }
             if !test_options.disable_tests {
-                gtoolkit.run_tests(packages)?;
+                let packages_with_tests = Self::packages_with_tests(packages, testable_packages);
+                if !packages_with_tests.is_empty() {
+                    Self::run_stage(
+                        application,
+                        test_options,
+                        durations,
+                        TestStage::Tests,
+                        || gtoolkit.run_tests(&packages_with_tests),
+                    )?;
+                }
             }
         } else {
-            gtoolkit.run_release_examples(test_options)?;
-            gtoolkit.run_release_slides(test_options)?;
-            gtoolkit.run_architectural_report()?;
+            Self::run_stage(
+                application,
+                test_options,
+                durations,
+                TestStage::Examples,
+                || gtoolkit.run_release_examples(test_options),
+            )?;
+            Self::run_stage(
+                application,
+                test_options,
+                durations,
+                TestStage::Slides,
+                || gtoolkit.run_release_slides(test_options),
+            )?;
+        }
+
+        for report in test_options.reports_to_export() {
+            let existing_entries = Self::workspace_entries(application)?;
+            Self::run_stage(
+                application,
+                test_options,
+                durations,
+                TestStage::Report(report.clone()),
+                || gtoolkit.run_report(&report),
+            )?;
+            if let Some(ref report_output) = test_options.report_output {
+                Self::collect_report_output(
+                    application,
+                    &existing_entries,
+                    report_output.join(&report),
+                )
+                .await?;
+            }
         }
 
         Ok(())
     }
+
+    /// Runs a test stage measuring its duration and, if needed, collects the JUnit XML it exported
+    fn run_stage(
+        application: &Application,
+        test_options: &TestOptions,
+        durations: &mut DurationReport,
+        stage: TestStage,
+        run: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        let started_at = SystemTime::now();
+        let started = Instant::now();
+
+        let result = run();
+
+        let duration = started.elapsed();
+        let results = if test_options.should_collect_results() {
+            JUnitReport::read_from_directory_since(application.workspace(), started_at)?
+        } else {
+            JUnitReport::new()
+        };
+        durations.add_stage(stage, duration, results);
+
+        result
+    }
+
+    /// Returns the names of the files and directories at the top level of the workspace,
+    /// together with the modification time of the files
+    fn workspace_entries(
+        application: &Application,
+    ) -> Result<BTreeMap<OsString, Option<SystemTime>>> {
+        let mut entries = BTreeMap::new();
+        for entry in std::fs::read_dir(application.workspace())? {
+            let entry = entry?;
+            entries.insert(entry.file_name(), Self::file_modification_time(&entry)?);
+        }
+        Ok(entries)
+    }
+
+    /// Directories are compared by name only, as their modification time changes
+    /// whenever anything is created within them
+    fn file_modification_time(entry: &DirEntry) -> Result<Option<SystemTime>> {
+        let metadata = entry.metadata()?;
+        Ok(if metadata.is_file() {
+            Some(metadata.modified()?)
+        } else {
+            None
+        })
+    }
+
+    /// Moves the files and directories that appeared in the workspace, and the files that were
+    /// written again, compared to the entries that existed before the report ran, into the destination directory
+    async fn collect_report_output(
+        application: &Application,
+        existing_entries: &BTreeMap<OsString, Option<SystemTime>>,
+        destination: PathBuf,
+    ) -> Result<()> {
+        tokio::fs::create_dir_all(&destination).await?;
+        let destination = to_absolute::canonicalize(&destination)
+            .map_err(|error| InstallerError::CanonicalizeError(destination, error))?;
+
+        let ignored_entries = REPORT_IGNORED_ENTRIES
+            .iter()
+            .map(|pattern| WildMatch::new(pattern))
+            .collect::<Vec<WildMatch>>();
+
+        for entry in std::fs::read_dir(application.workspace())? {
+            let entry = entry?;
+            if existing_entries.get(&entry.file_name())
+                == Some(&Self::file_modification_time(&entry)?)
+            {
+                continue;
+            }
+
+            let path = entry.path();
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            if ignored_entries.iter().any(|pattern| pattern.matches(&name))
+                || Self::contains_path(&path, &destination)
+            {
+                continue;
+            }
+
+            if path.is_dir() {
+                FolderToMove::new(&path, destination.join(&name))
+                    .move_folder()
+                    .await?;
+            } else {
+                FileToMove::new(&path, &destination).move_file().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the path is the given directory or one of its parents
+    fn contains_path(path: &Path, directory: &Path) -> bool {
+        to_absolute::canonicalize(path)
+            .map(|path| directory.starts_with(path))
+            .unwrap_or(false)
+    }
+
+    /// Returns the names of testable packages in the image matching the given options
+    pub async fn list_packages(
+        &self,
+        application: &Application,
+        list_options: &ListPackagesOptions,
+    ) -> Result<Vec<String>> {
+        let packages = application
+            .gtoolkit()
+            .list_testable_packages()?
+            .into_iter()
+            .filter(|package| {
+                if list_options.examples {
+                    package.has_examples
+                } else if list_options.tests {
+                    package.has_tests
+                } else {
+                    true
+                }
+            })
+            .collect::<Vec<TestablePackage>>();
+
+        if list_options.patterns.is_empty() {
+            Ok(packages.into_iter().map(|package| package.name).collect())
+        } else {
+            Self::expand_patterns(&list_options.patterns, &packages)
+        }
+    }
+
+    /// Returns true if the packages to test or to skip must be resolved by the image
+    fn should_resolve_packages(test_options: &TestOptions) -> bool {
+        test_options.packages.is_some() || test_options.skip_packages.is_some() || test_options.list
+    }
+
+    /// Expand glob patterns of the packages to test and to skip into the exact package names
+    /// known by the image. Selected packages do not include the skipped ones.
+    /// When listing all packages is requested and no packages are specified, selects every testable package.
+    fn resolve_test_options(
+        test_options: &TestOptions,
+        testable_packages: &[TestablePackage],
+    ) -> Result<TestOptions> {
+        if !Self::should_resolve_packages(test_options) {
+            return Ok(test_options.clone());
+        }
+
+        let skip_packages = test_options
+            .skip_packages
+            .as_ref()
+            .map(|patterns| Self::expand_patterns(patterns, testable_packages))
+            .transpose()?;
+
+        let packages = match test_options.packages {
+            Some(ref patterns) => Some(Self::expand_patterns(patterns, testable_packages)?),
+            None if test_options.list => Some(
+                testable_packages
+                    .iter()
+                    .map(|package| package.name.clone())
+                    .collect(),
+            ),
+            None => None,
+        }
+        .map(|packages: Vec<String>| {
+            packages
+                .into_iter()
+                .filter(|package| {
+                    !skip_packages
+                        .as_ref()
+                        .is_some_and(|skip_packages| skip_packages.contains(package))
+                })
+                .collect::<Vec<String>>()
+        });
+
+        let mut resolved_options = test_options.clone();
+        resolved_options.packages = packages;
+        resolved_options.skip_packages = skip_packages;
+        Ok(resolved_options)
+    }
+
+    /// Pharo's `test` runner can not skip packages, therefore we only pass the selected packages
+    /// with TestCase, which no longer include the skipped ones
+    fn packages_with_tests(
+        packages: &[String],
+        testable_packages: &[TestablePackage],
+    ) -> Vec<String> {
+        testable_packages
+            .iter()
+            .filter(|package| package.has_tests && packages.contains(&package.name))
+            .map(|package| package.name.clone())
+            .collect()
+    }
+
+    /// Returns names of the packages matching any of the patterns in the order they are known by the image.
+    /// Fails if any of the patterns does not match a package.
+    fn expand_patterns(patterns: &[String], packages: &[TestablePackage]) -> Result<Vec<String>> {
+        let matchers = patterns
+            .iter()
+            .map(|pattern| WildMatch::new(pattern))
+            .collect::<Vec<WildMatch>>();
+
+        for (pattern, matcher) in patterns.iter().zip(matchers.iter()) {
+            if !packages
+                .iter()
+                .any(|package| matcher.matches(package.name.as_str()))
+            {
+                return InstallerError::PackagePatternDidNotMatch(pattern.clone()).into();
+            }
+        }
+
+        Ok(packages
+            .iter()
+            .filter(|package| {
+                matchers
+                    .iter()
+                    .any(|matcher| matcher.matches(package.name.as_str()))
+            })
+            .map(|package| package.name.clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filetime::FileTime;
+
+    fn testable_packages() -> Vec<TestablePackage> {
+        [
+            ("GToolkit-Coder", true, false),
+            ("GToolkit-Coder-Examples", true, true),
+            ("GToolkit-Deprecated", true, false),
+            ("Brick", true, true),
+            ("Brick-Deprecated", false, true),
+        ]
+        .into_iter()
+        .map(|(name, has_examples, has_tests)| TestablePackage {
+            name: name.to_string(),
+            has_examples,
+            has_tests,
+        })
+        .collect()
+    }
+
+    fn resolve(arguments: &[&str]) -> Result<TestOptions> {
+        let test_options =
+            TestOptions::parse_from(std::iter::once("test").chain(arguments.iter().copied()));
+        Tester::resolve_test_options(&test_options, &testable_packages())
+    }
+
+    #[test]
+    fn expands_package_patterns() {
+        let test_options = resolve(&["--packages", "GToolkit-*", "Brick"]).unwrap();
+        assert_eq!(
+            test_options.packages.unwrap(),
+            vec![
+                "GToolkit-Coder",
+                "GToolkit-Coder-Examples",
+                "GToolkit-Deprecated",
+                "Brick"
+            ]
+        );
+        assert!(test_options.skip_packages.is_none());
+    }
+
+    #[test]
+    fn subtracts_skipped_packages() {
+        let test_options = resolve(&[
+            "--packages",
+            "GToolkit-*",
+            "Brick*",
+            "--skip-packages",
+            "*-Deprecated",
+        ])
+        .unwrap();
+        assert_eq!(
+            test_options.packages.unwrap(),
+            vec!["GToolkit-Coder", "GToolkit-Coder-Examples", "Brick"]
+        );
+        assert_eq!(
+            test_options.skip_packages.unwrap(),
+            vec!["GToolkit-Deprecated", "Brick-Deprecated"]
+        );
+    }
+
+    #[test]
+    fn lists_all_packages_except_skipped() {
+        let test_options = resolve(&["--list", "--skip-packages", "GToolkit-*"]).unwrap();
+        assert_eq!(
+            test_options.packages.unwrap(),
+            vec!["Brick", "Brick-Deprecated"]
+        );
+    }
+
+    #[test]
+    fn unmatched_pattern_is_an_error() {
+        for arguments in [["--packages", "Bloc-*"], ["--skip-packages", "Bloc-*"]] {
+            assert!(matches!(
+                resolve(&arguments),
+                Err(InstallerError::PackagePatternDidNotMatch(pattern)) if pattern == "Bloc-*"
+            ));
+        }
+    }
+
+    #[test]
+    fn keeps_options_without_packages() {
+        let test_options = resolve(&[]).unwrap();
+        assert!(test_options.packages.is_none());
+        assert!(test_options.skip_packages.is_none());
+    }
+
+    #[test]
+    fn skipped_packages_are_not_run_by_test_case() {
+        let test_options =
+            resolve(&["--packages", "Brick*", "--skip-packages", "*-Deprecated"]).unwrap();
+        assert_eq!(
+            Tester::packages_with_tests(&test_options.packages.unwrap(), &testable_packages()),
+            vec!["Brick"]
+        );
+
+        let test_options = resolve(&["--packages", "GToolkit-*", "Brick*"]).unwrap();
+        assert_eq!(
+            Tester::packages_with_tests(&test_options.packages.unwrap(), &testable_packages()),
+            vec!["GToolkit-Coder-Examples", "Brick", "Brick-Deprecated"]
+        );
+    }
+
+    #[tokio::test]
+    async fn collects_the_entries_written_by_a_report() {
+        let directory = tempfile::tempdir().unwrap();
+        let workspace = directory.path().join("workspace");
+        std::fs::create_dir_all(workspace.join("gt-extra")).unwrap();
+        let application = Application::for_tests(&workspace);
+        std::fs::write(application.image(), b"image").unwrap();
+        std::fs::write(workspace.join("architecture.html"), b"first run").unwrap();
+        std::fs::write(workspace.join("notes.txt"), b"notes").unwrap();
+        for file in ["architecture.html", "notes.txt"] {
+            filetime::set_file_mtime(workspace.join(file), FileTime::from_unix_time(1000, 0))
+                .unwrap();
+        }
+
+        let existing_entries = Tester::workspace_entries(&application).unwrap();
+
+        // the report writes its output while the image is saved
+        std::fs::write(application.image(), b"saved image").unwrap();
+        std::fs::write(workspace.join("architecture.html"), b"second run").unwrap();
+        std::fs::write(workspace.join("architecture.xml"), b"<testsuites/>").unwrap();
+        std::fs::create_dir(workspace.join("diagrams")).unwrap();
+        std::fs::write(workspace.join("diagrams").join("packages.svg"), b"svg").unwrap();
+        std::fs::write(workspace.join("gt-extra").join("cache"), b"cache").unwrap();
+
+        let report_output = directory.path().join("reports").join(DEFAULT_REPORT);
+        Tester::collect_report_output(&application, &existing_entries, report_output.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(report_output.join("architecture.html")).unwrap(),
+            b"second run"
+        );
+        assert!(report_output.join("architecture.xml").exists());
+        assert!(report_output.join("diagrams").join("packages.svg").exists());
+        assert!(!report_output.join("notes.txt").exists());
+        assert!(!report_output.join("gt-extra").exists());
+        assert!(!report_output.join("GlamorousToolkit.image").exists());
+
+        assert!(workspace.join("notes.txt").exists());
+        assert!(workspace.join("gt-extra").join("cache").exists());
+        assert!(application.image().exists());
+    }
 }
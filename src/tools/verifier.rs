@@ -0,0 +1,399 @@
+use std::path::{Path, PathBuf};
+
+use clap::{ArgEnum, Parser};
+use wildmatch::WildMatch;
+
+use crate::{
+    signature_path, Application, ArchiveContent, ArchiveFormat, InstallerError, ManifestFormat,
+    Package, PlatformOS, ReleaseManifest, Result, SbomFormat, SignatureVerifier, TentativeManifest,
+    CHECKING, DEBIAN_INSTALLATION_DIRECTORY, SERIALIZATION_FILE, SPARKLE, TENTATIVE_MANIFEST_FILE,
+};
+
+/// Entries that a tentative package may contain in addition to the release entries
+const TENTATIVE_ENTRIES: [&str; 4] = [
+    SERIALIZATION_FILE,
+    TENTATIVE_MANIFEST_FILE,
+    "Dockerfile",
+    "docker-image",
+];
+/// Entries that are created by the Android build tools within an .apk
+const ANDROID_ENTRIES: [&str; 6] = [
+    "AndroidManifest.xml",
+    "assets",
+    "classes.dex",
+    "resources.arsc",
+    "META-INF",
+    "res",
+];
+
+#[derive(Parser, Debug, Clone)]
+pub struct VerifyPackageOptions {
+    /// Path to the package created by `package-release` or `package-tentative`
+    #[clap(parse(from_os_str))]
+    pub package: PathBuf,
+    /// The target the package was created for. Defaults to the host platform
+    #[clap(long, arg_enum)]
+    pub target: Option<PlatformOS>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct VerifySignatureOptions {
+    /// Path to the signed file
+    #[clap(parse(from_os_str))]
+    pub file: PathBuf,
+    /// Path to the detached signature. Defaults to <file>.sig
+    #[clap(long, parse(from_os_str))]
+    pub signature: Option<PathBuf>,
+    /// A minisign public key, either a path to a .pub file or the base64 encoded key.
+    /// Defaults to the FEENK_PUBLIC_KEY environment variable
+    #[clap(long)]
+    pub public_key: Option<String>,
+}
+
+pub struct PackageVerifier;
+
+impl PackageVerifier {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Checks that the package contains the image with its resources and the GlamorousToolkit App
+    /// for the target, and nothing else. The package and its files must also match the checksums
+    /// of the release manifest next to it or of the manifest embedded in a tentative package.
+    /// Prints every missing or unexpected entry and every checksum mismatch
+    pub fn verify(
+        &self,
+        application: &Application,
+        verify_options: &VerifyPackageOptions,
+    ) -> Result<()> {
+        let package = verify_options.package.as_path();
+        let target = verify_options
+            .target
+            .unwrap_or_else(|| application.host_platform());
+
+        println!(
+            "{}Verifying {} for {}...",
+            CHECKING,
+            package.display(),
+            target.as_str()
+        );
+
+        let content = ArchiveContent::read(package, &[SERIALIZATION_FILE])?;
+        let mut problems = Self::problems(&content, target, ArchiveFormat::from_path(package));
+        problems.extend(Self::checksum_problems(package, &content)?);
+
+        for problem in &problems {
+            println!("  {}", problem);
+        }
+
+        if !problems.is_empty() {
+            return InstallerError::PackageVerificationFailed(
+                package.to_path_buf(),
+                problems.len(),
+            )
+            .into();
+        }
+
+        println!("{}{} is valid", SPARKLE, package.display());
+        Ok(())
+    }
+
+    /// Checks that the detached signature of a file was created with the secret key of a given public key
+    pub fn verify_signature(&self, signature_options: &VerifySignatureOptions) -> Result<()> {
+        let file = signature_options.file.as_path();
+        let signature = signature_options
+            .signature
+            .clone()
+            .unwrap_or_else(|| signature_path(file));
+
+        let verifier = SignatureVerifier::from_key_or_env(signature_options.public_key.as_deref())?
+            .ok_or(InstallerError::PublicKeyNotConfigured)?;
+
+        println!("{}Verifying signature of {}...", CHECKING, file.display());
+        verifier.verify(file, &signature)?;
+        println!("{}Signature of {} is valid", SPARKLE, file.display());
+        Ok(())
+    }
+
+    /// Compares the package with the release manifests next to it,
+    /// and the files of a tentative package with its embedded manifest
+    fn checksum_problems(package: &Path, content: &ArchiveContent) -> Result<Vec<String>> {
+        let mut problems = vec![];
+
+        for format in ManifestFormat::value_variants() {
+            let manifest = ReleaseManifest::manifest_path(package, *format);
+            if manifest.exists() {
+                problems
+                    .extend(ReleaseManifest::read(&manifest, *format)?.package_problems(package)?);
+            }
+        }
+
+        if content
+            .entries()
+            .iter()
+            .any(|entry| entry == TENTATIVE_MANIFEST_FILE)
+        {
+            let (_, tentative_problems) = TentativeManifest::archive_problems(package)?;
+            problems.extend(tentative_problems);
+        }
+
+        Ok(problems)
+    }
+
+    fn problems(
+        content: &ArchiveContent,
+        target: PlatformOS,
+        format: ArchiveFormat,
+    ) -> Vec<String> {
+        // on Android the image and its resources are packaged as assets
+        let workspace_directory = if target.is_android() { "assets/" } else { "" };
+
+        let mut expected = ["*.image", "*.changes", "*.sources", "gt-extra"]
+            .iter()
+            .map(|each| format!("{}{}", workspace_directory, each))
+            .collect::<Vec<String>>();
+        expected.extend(
+            Package::gtoolkit_app_entry_names_for_target(target)
+                .into_iter()
+                .map(|each| each.to_string()),
+        );
+
+        let mut allowed = if target.is_android() {
+            ANDROID_ENTRIES.to_vec()
+        } else {
+            TENTATIVE_ENTRIES.to_vec()
+        }
+        .into_iter()
+        .map(|each| each.to_string())
+        .collect::<Vec<String>>();
+        // release packages include a software bill of materials next to the image
+        allowed.extend(
+            SbomFormat::value_variants()
+                .iter()
+                .map(|format| format!("{}{}", workspace_directory, format.file_name())),
+        );
+
+        // a Debian package is verified by the content of its installation directory
+        let installation_directory = format!("{}/", DEBIAN_INSTALLATION_DIRECTORY);
+        let mut roots = content
+            .entries()
+            .iter()
+            .filter_map(|entry| match format {
+                ArchiveFormat::Deb => entry.strip_prefix(installation_directory.as_str()),
+                _ => Some(entry.as_str()),
+            })
+            .map(|entry| Self::root_of(entry, target))
+            .collect::<Vec<String>>();
+        roots.sort();
+        roots.dedup();
+
+        let mut problems = vec![];
+
+        for pattern in &expected {
+            let matcher = WildMatch::new(pattern);
+            if !roots.iter().any(|root| matcher.matches(root)) {
+                problems.push(format!("missing: {}", pattern));
+            }
+        }
+
+        for root in &roots {
+            let is_expected = expected
+                .iter()
+                .any(|pattern| WildMatch::new(pattern).matches(root));
+            if !is_expected && !allowed.contains(root) {
+                problems.push(format!("unexpected: {}", root));
+            }
+        }
+
+        if let Some(serialized) = content.file_content(SERIALIZATION_FILE) {
+            match serde_yaml::from_str::<Application>(serialized) {
+                Ok(application) => {
+                    let image = format!(
+                        "{}{}.{}",
+                        workspace_directory,
+                        application.image_name(),
+                        application.image_extension()
+                    );
+                    if !roots.contains(&image) {
+                        problems.push(format!(
+                            "{} refers to {} which is not in the package",
+                            SERIALIZATION_FILE, image
+                        ));
+                    }
+                }
+                Err(error) => {
+                    problems.push(format!("invalid {}: {}", SERIALIZATION_FILE, error));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Returns the top level entry that contains a given entry.
+    /// Assets of an Android package are treated as top level entries
+    fn root_of(entry: &str, target: PlatformOS) -> String {
+        let mut components = Path::new(entry)
+            .iter()
+            .map(|component| component.to_string_lossy().to_string());
+        let root = components.next().unwrap_or_default();
+
+        if target.is_android() && root == "assets" {
+            if let Some(asset) = components.next() {
+                return format!("{}/{}", root, asset);
+            }
+        }
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomerLevel, ManifestFile, Reproducible, ToReproducibleZip};
+
+    const TARGET: PlatformOS = PlatformOS::LinuxX8664;
+
+    /// Creates a workspace with the image, its resources and the App for Linux
+    fn create_workspace(workspace: &Path) -> Application {
+        let application = Application::for_tests(workspace);
+        for file in [
+            "GlamorousToolkit.image",
+            "GlamorousToolkit.changes",
+            "Pharo.sources",
+            "gt-extra/feenk.png",
+            "bin/GlamorousToolkit",
+            "lib/libGlamorousToolkit.so",
+        ] {
+            let file = workspace.join(file);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, file.to_string_lossy().as_bytes()).unwrap();
+        }
+        application
+    }
+
+    fn package(workspace: &Path, entries: &[&str], package: &Path) -> PathBuf {
+        ToReproducibleZip::new(package, Reproducible::from_source_date_epoch(0).unwrap())
+            .paths(entries.iter().map(|entry| workspace.join(entry)).collect())
+            .zip()
+            .unwrap()
+    }
+
+    fn release_package(application: &Application, entries: &[&str], package: &Path) -> PathBuf {
+        let package = self::package(application.workspace(), entries, package);
+        ReleaseManifest::new(
+            application,
+            TARGET,
+            CustomerLevel::Regular,
+            &package,
+            ManifestFile::collect_archive(&package).unwrap(),
+        )
+        .unwrap()
+        .write(&package, ManifestFormat::Json)
+        .unwrap();
+        package
+    }
+
+    fn verify(application: &Application, package: &Path) -> Result<()> {
+        PackageVerifier::new().verify(
+            application,
+            &VerifyPackageOptions {
+                package: package.to_path_buf(),
+                target: Some(TARGET),
+            },
+        )
+    }
+
+    const RELEASE_ENTRIES: [&str; 6] = [
+        "GlamorousToolkit.image",
+        "GlamorousToolkit.changes",
+        "Pharo.sources",
+        "gt-extra",
+        "bin",
+        "lib",
+    ];
+
+    #[test]
+    fn accepts_a_complete_package() {
+        let directory = tempfile::tempdir().unwrap();
+        let application = create_workspace(&directory.path().join("workspace"));
+        let package = release_package(
+            &application,
+            &RELEASE_ENTRIES,
+            &directory.path().join("release/GlamorousToolkit.zip"),
+        );
+
+        verify(&application, &package).unwrap();
+    }
+
+    #[test]
+    fn rejects_a_package_with_a_missing_entry() {
+        let directory = tempfile::tempdir().unwrap();
+        let application = create_workspace(&directory.path().join("workspace"));
+        let package = release_package(
+            &application,
+            &RELEASE_ENTRIES[..5],
+            &directory.path().join("release/GlamorousToolkit.zip"),
+        );
+
+        assert!(matches!(
+            verify(&application, &package),
+            Err(InstallerError::PackageVerificationFailed(_, 1))
+        ));
+    }
+
+    #[test]
+    fn rejects_a_package_with_a_wrong_checksum() {
+        let directory = tempfile::tempdir().unwrap();
+        let application = create_workspace(&directory.path().join("workspace"));
+        let package = release_package(
+            &application,
+            &RELEASE_ENTRIES,
+            &directory.path().join("release/GlamorousToolkit.zip"),
+        );
+
+        let manifest = ReleaseManifest::manifest_path(&package, ManifestFormat::Json);
+        let mut release_manifest = ReleaseManifest::read(&manifest, ManifestFormat::Json).unwrap();
+        release_manifest.sha256 = "0".repeat(64);
+        release_manifest
+            .write(&package, ManifestFormat::Json)
+            .unwrap();
+
+        assert!(matches!(
+            verify(&application, &package),
+            Err(InstallerError::PackageVerificationFailed(_, 1))
+        ));
+    }
+
+    #[test]
+    fn rejects_a_tentative_package_with_a_wrong_checksum() {
+        let directory = tempfile::tempdir().unwrap();
+        let application = create_workspace(&directory.path().join("workspace"));
+        application.serialize_into_file().unwrap();
+
+        let mut entries = RELEASE_ENTRIES.to_vec();
+        entries.push(SERIALIZATION_FILE);
+        let packaged = entries
+            .iter()
+            .map(|entry| application.workspace().join(entry))
+            .collect::<Vec<PathBuf>>();
+
+        let mut manifest = TentativeManifest::new(&application, &packaged).unwrap();
+        manifest.files[0].sha256 = "0".repeat(64);
+        manifest
+            .write(application.workspace().join(TENTATIVE_MANIFEST_FILE))
+            .unwrap();
+        entries.push(TENTATIVE_MANIFEST_FILE);
+
+        let package = package(
+            application.workspace(),
+            &entries,
+            &directory.path().join("GlamorousToolkit-tentative.zip"),
+        );
+
+        assert!(matches!(
+            verify(&application, &package),
+            Err(InstallerError::PackageVerificationFailed(_, 1))
+        ));
+    }
+}